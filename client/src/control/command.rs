@@ -5,7 +5,10 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use thread_safe_utils::signal::SignallableData;
+use thread_safe_utils::{
+    queue::{Prioritized, Priority},
+    signal::SignallableData,
+};
 
 pub type CommandID = usize;
 
@@ -48,6 +51,22 @@ pub enum Command {
     Quit,
 }
 
+impl Prioritized for Command {
+    /// `Quit` overtakes anything still waiting to be sent or run
+    fn priority(&self) -> Priority {
+        match self {
+            Command::Quit => Priority::High,
+            _ => Priority::Normal,
+        }
+    }
+}
+
+impl Prioritized for Instruction {
+    fn priority(&self) -> Priority {
+        self.command.priority()
+    }
+}
+
 impl Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
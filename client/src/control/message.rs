@@ -1,5 +1,6 @@
 use logger::LogMessage;
 use serde::{Deserialize, Serialize};
+use thread_safe_utils::queue::{Prioritized, Priority};
 
 use super::command::CommandID;
 
@@ -19,6 +20,17 @@ pub enum DataMessage {
     ThreadId(u32),
 }
 
+impl Prioritized for Message {
+    /// Acknowledgements go ahead of everything else. Logs, data and `Exiting` share a lane, so the
+    /// Master sees them in the order they were sent.
+    fn priority(&self) -> Priority {
+        match self {
+            Message::Ready | Message::Ack(_) => Priority::High,
+            Message::DataMessage(_) | Message::Exiting | Message::Log(_) => Priority::Normal,
+        }
+    }
+}
+
 impl From<DataMessage> for Message {
     fn from(value: DataMessage) -> Self {
         Message::DataMessage(value)
@@ -17,8 +17,12 @@ pub enum IpcError {
     ThreadNotRunning,
     #[error("Ipc Queue Mutex was poisoned")]
     MutexPoisoned,
-    #[error("Ipc Queue signal set")]
-    Signalled,
+    #[error("Ipc Queue closed")]
+    Closed,
+    #[error("Ipc Queue cancelled")]
+    Cancelled,
+    #[error("Ipc Queue is full")]
+    QueueFull,
     #[error("Named pipe was closed")]
     PipeClosed,
 }
@@ -27,7 +31,9 @@ impl From<ThreadSafeQueueError> for IpcError {
     fn from(val: ThreadSafeQueueError) -> Self {
         match val {
             ThreadSafeQueueError::MutexPoison => IpcError::MutexPoisoned,
-            ThreadSafeQueueError::StatusNotOk => IpcError::Signalled,
+            ThreadSafeQueueError::Closed => IpcError::Closed,
+            ThreadSafeQueueError::Cancelled => IpcError::Cancelled,
+            ThreadSafeQueueError::Full => IpcError::QueueFull,
         }
     }
 }
@@ -1,24 +1,34 @@
-use std::{thread::JoinHandle, time::{Duration, Instant}};
+use std::{sync::Arc, thread::JoinHandle, time::{Duration, Instant}};
 
 use error::IpcError;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use serde::{Deserialize, Serialize};
-use thread_safe_utils::{queue::ThreadSafeQueue, signal::Signal};
+use thread_safe_utils::{
+    queue::{Prioritized, ThreadSafeQueue},
+    signal::{CancellationToken, IdleSignal, Signal, wait_any},
+};
 
 mod error;
 pub mod master;
 pub mod slave;
 pub mod control;
 
-trait PipeData: Serialize + for<'a> Deserialize<'a> + Send + 'static {}
-impl<T> PipeData for T where T: Serialize + for<'a> Deserialize<'a> + Send + 'static {}
+/// Messages an [`IpcEnd`] holds before `send` starts waiting for the send thread to catch up
+const SEND_QUEUE_CAPACITY: usize = 1024;
+
+trait PipeData: Serialize + for<'a> Deserialize<'a> + Prioritized + Send + 'static {}
+impl<T> PipeData for T where T: Serialize + for<'a> Deserialize<'a> + Prioritized + Send + 'static {}
 
 #[allow(dead_code)]
 struct IpcEnd<S: PipeData, R: PipeData> {
     send_queue: ThreadSafeQueue<S>,
     recv_queue: ThreadSafeQueue<R>,
     send_thread: Option<JoinHandle<Result<(), IpcError>>>,
+    /// Set by the send thread as it finishes
+    send_done: Arc<IdleSignal>,
     recv_thread: Option<JoinHandle<Result<(), IpcError>>>,
+    /// How long dropping waits for queued messages to be sent
+    send_cleanup: Duration,
 }
 
 unsafe impl<S: PipeData, R: PipeData> Send for IpcEnd<S, R> {}
@@ -26,9 +36,12 @@ unsafe impl<S: PipeData, R: PipeData> Sync for IpcEnd<S, R> {}
 
 impl<S: PipeData, R: PipeData> Drop for IpcEnd<S, R> {
     fn drop(&mut self) {
-        self.send_queue.set_signal(true);
-        self.recv_queue.set_signal(true);
+        // Anything already queued still gets sent, unless that takes longer than `send_cleanup`
+        self.send_queue.close();
+        self.recv_queue.cancel();
         if let Some(thread) = self.send_thread.take() {
+            let _ = wait_any(&[self.send_done.as_ref()], Some(Instant::now() + self.send_cleanup));
+            self.send_queue.cancel();
             let _ = thread.join();
         }
         if let Some(thread) = self.recv_thread.take() {
@@ -55,23 +68,24 @@ impl<S: PipeData, R: PipeData> IpcEnd<S, R> {
         };
         let sender = sender;
         let receiver = receiver;
-        let send_queue = ThreadSafeQueue::new();
+        let send_queue = ThreadSafeQueue::bounded(SEND_QUEUE_CAPACITY);
         let recv_queue = ThreadSafeQueue::new();
+        let send_done = Arc::new(IdleSignal::new());
         let send_thread = {
             let send_queue = send_queue.clone();
+            let send_done = send_done.clone();
             Some(std::thread::spawn(move || -> Result<(), IpcError> {
-                // Dequeue will return ThreadSafeQueueError::StatusNotOk on signal
+                // Dequeue drains the queue once it is closed and stops straight away if it is cancelled
+                let mut result = Ok(());
                 while let Ok(data) = send_queue.dequeue() {
-                    sender.send(data).inspect_err(|_e| {
-                        send_queue.set_signal(true);
-                    })?;
-                }
-                // Give threads a chance to place important messages in the queue
-                let deadline = Instant::now() + send_cleanup;
-                while let Ok(Some(data)) = send_queue.try_dequeue_timeout(deadline.duration_since(Instant::now())) {
-                    sender.send(data)?;
+                    if let Err(e) = sender.send(data) {
+                        send_queue.cancel();
+                        result = Err(e.into());
+                        break;
+                    }
                 }
-                Ok(())
+                send_done.set_signal(true);
+                result
             }))
         };
         let recv_thread = {
@@ -80,16 +94,21 @@ impl<S: PipeData, R: PipeData> IpcEnd<S, R> {
                 loop {
                     match receiver.try_recv_timeout(recv_timeout) {
                         Ok(data) => {
-                            if recv_queue.enqueue(data).is_err() {
+                            if recv_queue.enqueue_prioritized(data).is_err() {
                                 break;
                             }
                         }
                         Err(e) => match e {
                             ipc_channel::ipc::TryRecvError::IpcError(error) => {
-                                recv_queue.set_signal(true);
+                                // Whatever was received before the error can still be read
+                                recv_queue.close();
                                 return Err(error.into());
                             }
-                            ipc_channel::ipc::TryRecvError::Empty => {}
+                            ipc_channel::ipc::TryRecvError::Empty => {
+                                if recv_queue.is_cancelled() {
+                                    break;
+                                }
+                            }
                         },
                     }
                 }
@@ -100,16 +119,23 @@ impl<S: PipeData, R: PipeData> IpcEnd<S, R> {
             send_queue,
             recv_queue,
             send_thread,
+            send_done,
             recv_thread,
+            send_cleanup,
         }
     }
 
+    /// Stops both IPC threads straight away once `token` is cancelled, abandoning anything queued
+    pub fn cancel_on(&self, token: &CancellationToken) {
+        self.send_queue.cancel_on(token);
+        self.recv_queue.cancel_on(token);
+    }
+
     pub fn send(&self, data: S) -> Result<(), ()> {
         if let Some(thread) = self.send_thread.as_ref() {
             if !thread.is_finished() {
-                // ThreadSafeQueue should never have its mutex poisoned, so this shouldn't be an issue
-                self.send_queue.enqueue(data).unwrap();
-                return Ok(());
+                // Waits while the queue is full, fails if the send thread stops in the meantime
+                return self.send_queue.enqueue_prioritized(data).map_err(|_| ());
             }
         }
         Err(())
@@ -6,10 +6,10 @@ use std::{
 
 use client_state::ClientState;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
-use logger::{LogMessage, LogWorker, severity::LogSeverity};
+use logger::{LogMessage, LogWorker, log_error, log_info, log_warn};
 use thread_safe_utils::{
     queue::ThreadSafeQueue,
-    signal::{Signal, SignallableData},
+    signal::{CancellationToken, Signal, SignallableData},
 };
 
 mod client_state;
@@ -33,6 +33,8 @@ pub struct Master {
     ipc: IpcEnd<Instruction, Message>,
     state: Arc<SignallableData<ClientState>>,
     logger: LogWorker,
+    /// A child of the token given to `new`, kept alive for as long as the master is
+    cancel: CancellationToken,
 }
 
 impl Drop for Master {
@@ -42,25 +44,41 @@ impl Drop for Master {
 }
 
 impl Master {
+    /// Cancelling `cancel` stops the IPC threads straight away and terminates the master
     pub fn new(
         sender: IpcSender<Instruction>,
         receiver: IpcReceiver<Message>,
         logger: LogWorker,
+        cancel: &CancellationToken,
     ) -> Self {
         let ipc = IpcEnd::new_with_timeout(sender, receiver, None, Some(Duration::from_secs(5)));
         let helper = Arc::new(SignallableData::<ClientState>::default());
+        let cancel = cancel.child();
+        ipc.cancel_on(&cancel);
+        let state = helper.clone();
+        cancel.on_cancel(move || {
+            state.set_signal(true);
+        });
         Self {
             ipc,
             state: helper.clone(),
             logger,
+            cancel,
         }
     }
 
+    /// Asks the client to quit, letting anything already sent be delivered first.
+    /// [`cancel`](Self::cancel) stops the master without waiting.
     pub fn terminate(&self) {
         let _ = self.send(Command::Quit);
         self.state.set_signal(true);
     }
 
+    /// Stops the IPC threads straight away, abandoning anything not yet sent or received
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
     fn log(&self, message: LogMessage) {
         let _ = self.logger.log(message);
     }
@@ -70,10 +88,7 @@ impl Master {
         let id = lock.next_id();
         let (data, pending_inst, pending_cmd) = Instruction::new(id, data);
         lock.add_pending_inst(pending_inst);
-        self.log(LogMessage::new(
-            logger::severity::LogSeverity::Info,
-            format!("Send Command {} with ID {}", data.command, data.id),
-        ));
+        log_info!(self, { id = data.id, command = data.command }, "Send Command");
         self.ipc.send(data).map_err(|_| IpcError::PipeClosed)?;
         Ok(pending_cmd)
     }
@@ -81,46 +96,28 @@ impl Master {
     pub fn try_recv_one(&self) -> Result<Option<DataMessage>, IpcError> {
         let message = self.ipc.recv().inspect_err(|e| {
             self.terminate();
-            self.log(LogMessage::new(
-                LogSeverity::Error,
-                format!("An error occured while receiving a message. {}", e),
-            ));
+            log_error!(self, "An error occured while receiving a message. {}", e);
         })?;
         match message {
             Message::Ready => {
-                self.log(LogMessage::new(LogSeverity::Info, "Client ready"));
+                log_info!(self, "Client ready");
             }
             Message::Ack(id) => {
                 let mut lock = self.state.lock().unwrap();
                 let (inst, has_strong_ref) = lock.acknowledge_instruction(id);
-                let log_msg = if let Some(inst) = inst {
-                    LogMessage::new(
-                        logger::severity::LogSeverity::Info,
-                        format!(
-                            "Received acknowledgement for ID {} ({}){}",
-                            id,
-                            inst.command,
-                            if !has_strong_ref {
-                                " (No strong refs remain)"
-                            } else {
-                                ""
-                            }
-                        ),
-                    )
+                if let Some(inst) = inst {
+                    log_info!(
+                        self,
+                        { id = id, command = inst.command, strong_refs = has_strong_ref },
+                        "Received acknowledgement"
+                    );
                 } else {
-                    LogMessage::new(
-                        logger::severity::LogSeverity::Warning,
-                        format!(
-                            "Received acknowledgement for unknown command with ID {}",
-                            id
-                        ),
-                    )
-                };
-                self.log(log_msg);
+                    log_warn!(self, { id = id }, "Received acknowledgement for unknown command");
+                }
                 drop(lock);
             }
             Message::Exiting => {
-                self.log(LogMessage::new(LogSeverity::Info, "Client exiting..."));
+                log_info!(self, "Client exiting...");
             }
             Message::Log(dll_log_message) => {
                 self.log(dll_log_message);
@@ -1,3 +1,4 @@
+use logger::{log_debug, log_error, log_span};
 use num_format::{Locale, ToFormattedString};
 use std::{iter::Once, panic::AssertUnwindSafe, sync::Mutex};
 use widestring::Utf16String;
@@ -24,7 +25,7 @@ impl Slave {
             .collect::<Vec<_>>();
         let mut entries: Vec<Vec<u8>> = Vec::new();
         let mut walker = MemoryWalker::new();
-        let _ = self.log_debug("Beginning memory walk...");
+        let mut walk_span = log_span!(self.log_manager, "memory walk");
         let res = unsafe {
             walker.walk_unsafe(target.len().., |data, block| {
                 if !entries
@@ -33,27 +34,29 @@ impl Slave {
                 {
                     for i in 0..data.len().saturating_sub(target.len()) {
                         if data[i..].starts_with(&target) {
-                            let _ = self.log_debug(format!("Matched at {}", block));
+                            let _ = log_debug!(self, { block = block }, "Matched");
                             if let Some(data) = block.try_copy_range(i..) {
                                 entries.push(data);
                             }
                         }
                     }
                 }
-                true
+                !self.cancel.is_cancelled()
             })
         };
         match res {
             Ok(_info) => {}
             Err(e) => {
-                let _ = self.log_error(format!("Walk returned an error! {}", e));
+                let _ = log_error!(self, "Walk returned an error! {}", e);
                 return Err(IpcError::MutexPoisoned);
             }
         }
-        let _ = self.log_debug(format!(
-            "Finished memory walk, scanning {} regions for json entries",
-            entries.len()
-        ));
+        if self.cancel.is_cancelled() {
+            return Err(IpcError::Cancelled);
+        }
+        walk_span.record("entries", entries.len());
+        drop(walk_span);
+        let mut extract_span = log_span!(self.log_manager, "json extraction");
         let json = entries
             .into_iter()
             .filter_map(|entry| {
@@ -65,6 +68,7 @@ impl Slave {
                 self.search_for_json(utf16_string)
             })
             .collect::<Vec<_>>();
+        extract_span.record("json", json.len());
         Ok(json)
     }
 
@@ -1,8 +1,8 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
-use logger::{loggers::{file::{FileConflictBehavior, FileLogger}, filter::LogFilter, multi::MultiLogger}, severity::LogSeverity, LogManager, LogMessage, Logger};
-use thread_safe_utils::queue::ThreadSafeQueue;
+use logger::{config::{self, ConfigError, ConflictConfig, ExternalSinks, FormatterConfig, LogConfig, NamedSinkConfig, OverflowConfig, QueueConfig, RateLimitConfig, RotationConfig, SinkConfig}, log_error, log_info, log_span, log_verbose, severity::LogSeverity, LogError, LogManager, LogMessage, LogResult, Logger};
+use thread_safe_utils::{queue::{ThreadSafeQueue, ThreadSafeQueueError}, signal::CancellationToken};
 use windows::Win32::System::Threading::{GetCurrentProcessId, GetCurrentThreadId};
 
 use crate::{
@@ -11,19 +11,38 @@ use crate::{
 
 mod json;
 
+const LOG_FILE_MAX_SIZE: u64 = 16 * 1024 * 1024;
+const LOG_FILES_KEPT: usize = 5;
+const LOG_QUEUE_CAPACITY: usize = 4096;
+const DEBUG_LOGS_PER_SECOND: f64 = 100.0;
+const DEBUG_LOG_BURST: u32 = 500;
+/// The name the IPC logger is registered under as an external sink
+const IPC_SINK: &str = "ipc";
+const LOG_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
 struct IpcLogger {
     queue: ThreadSafeQueue<Message>
 }
 
 impl Logger for IpcLogger {
-    fn log(&mut self, message: &LogMessage) -> bool {
-        self.queue.enqueue(message.into()).is_ok()
+    /// Never waits for room, so a stalled pipe drops messages instead of holding up the other
+    /// loggers. A full queue isn't a failure, forwarding resumes once the Master catches up.
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        self.queue
+            .try_enqueue_prioritized(message.into())
+            .map_err(|e| match e.error() {
+                ThreadSafeQueueError::Full => LogError::Dropped,
+                _ => LogError::Queue(e.into()),
+            })
     }
 }
 
 pub struct Slave {
-    ipc: IpcEnd<Message, Instruction>,
+    // Declared before `ipc` so it is dropped first, draining its queue into the IPC logger while
+    // the IPC threads are still running
     log_manager: LogManager,
+    ipc: IpcEnd<Message, Instruction>,
+    cancel: CancellationToken,
 }
 
 // Logging functions
@@ -60,26 +79,100 @@ impl Slave {
 
 }
 
+/// The payload's logging pipeline unless another is given to [`Slave::with_log_config`]: every
+/// message goes to the Master over IPC and to a rotated logfmt file at `log_path`, with repeats
+/// collapsed and debug messages rate limited.
+pub fn default_log_config(log_path: impl Into<PathBuf>) -> LogConfig {
+    let file = SinkConfig::File {
+        path: log_path.into(),
+        conflict: ConflictConfig::RenameOld,
+        rotation: Some(RotationConfig {
+            max_size: Some(LOG_FILE_MAX_SIZE),
+            keep: Some(LOG_FILES_KEPT),
+            ..Default::default()
+        }),
+        // Written as logfmt, rather than the text default, so `logq` can read the payload's log
+        formatter: Some(FormatterConfig::Logfmt),
+    };
+    let multi = SinkConfig::Multi {
+        sinks: vec![
+            NamedSinkConfig {
+                name: Some(String::from(IPC_SINK)),
+                sink: SinkConfig::External { name: String::from(IPC_SINK) },
+            },
+            NamedSinkConfig { name: Some(String::from("file")), sink: file },
+        ],
+        retry: None,
+    };
+    let throttled = SinkConfig::Dedup {
+        ignore_fields: false,
+        sink: Box::new(SinkConfig::RateLimit {
+            limits: vec![RateLimitConfig {
+                severity: String::from("debug"),
+                per_second: DEBUG_LOGS_PER_SECOND,
+                burst: DEBUG_LOG_BURST,
+            }],
+            sink: Box::new(multi),
+        }),
+    };
+    LogConfig {
+        sink: SinkConfig::Filter {
+            directives: String::from("verbose"),
+            env: None,
+            sink: Box::new(throttled),
+        },
+        origin: Some(String::from("payload")),
+        // The memory walk can log far faster than IPC drains, keep the most recent messages
+        queue: Some(QueueConfig {
+            capacity: LOG_QUEUE_CAPACITY,
+            overflow: OverflowConfig::DropOldest,
+            lock_free: false,
+        }),
+    }
+}
+
 impl Slave {
+    /// Creates a slave logging through [`default_log_config`]. Panics if the log file can't be
+    /// opened.
     pub fn new(sender: IpcSender<Message>, receiver: IpcReceiver<Instruction>, log_path: impl Into<PathBuf>) -> Self {
+        Self::with_log_config(sender, receiver, &default_log_config(log_path))
+            .expect("Failed to build the payload's logger")
+    }
+
+    /// Creates a slave logging through the pipeline in `log_config`. Its `external` sink named
+    /// `ipc` sends messages to the Master.
+    pub fn with_log_config(
+        sender: IpcSender<Message>,
+        receiver: IpcReceiver<Instruction>,
+        log_config: &LogConfig,
+    ) -> Result<Self, ConfigError> {
         let ipc = IpcEnd::new(sender, receiver);
         let ipc_logger = IpcLogger { queue: ipc.send_queue.clone() };
-        let file_logger = FileLogger::new(log_path.into(), FileConflictBehavior::Overwrite).unwrap();
-        let multi_logger = MultiLogger::new().with_logger(ipc_logger).with_logger(file_logger);
-        let filter = LogFilter::new(LogSeverity::Verbose, multi_logger);
-        let log_manager = LogManager::new(filter);
-        Self {
+        let external = ExternalSinks::from([(String::from(IPC_SINK), Box::new(ipc_logger) as Box<dyn Logger + Send>)]);
+        let log_manager = config::build_with(log_config, external)?;
+        let _ = log_manager.install_log_bridge(LogSeverity::Debug);
+        let cancel = CancellationToken::new();
+        ipc.cancel_on(&cancel);
+        log_manager.stop_on(&cancel);
+        Ok(Self {
+            log_manager,
             ipc,
-            log_manager
-        }
+            cancel,
+        })
     }
+
+    /// Cancelling this stops the IPC threads, the logging thread and any running memory walk
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
     fn send(&self, msg: Message) -> Result<(), IpcError> {
         //self.log_verbose(format!("Sent `{:?}` message", msg))?;
         self.ipc.send(msg).map_err(|_| IpcError::SendError)
     }
 
     fn acknowledge(&self, inst: &Instruction) -> Result<(), IpcError> {
-        self.log_verbose(format!("Acknowledging instruction {} ( {} )", inst.id, inst.command))?;
+        log_verbose!(self, { id = inst.id, command = inst.command }, "Acknowledging instruction")?;
         self.send(Message::Ack(inst.id))
     }
 
@@ -89,13 +182,17 @@ impl Slave {
             self.acknowledge(&inst)?;
             match inst.command {
                 Command::Quit => {
-                    let _ = self.log_info("Quitting...");
+                    let _ = log_info!(self, "Quitting...");
                     break;
                 },
                 Command::FindJSON => {
+                    let _scan_span = log_span!(self.log_manager, "json scan");
                     match self.locate_json() {
-                        Ok(strs) => self.send(DataMessage::Json(strs).into())?,
-                        Err(e) => self.log_error(e.to_string())?,
+                        Ok(strs) => {
+                            let _send_span = log_span!(self.log_manager, "send json");
+                            self.send(DataMessage::Json(strs).into())?
+                        }
+                        Err(e) => log_error!(self, "{}", e)?,
                     }
                 },
                 Command::GetThreadId => {
@@ -108,6 +205,7 @@ impl Slave {
                 }
             }
         }
+        let _ = self.log_manager.flush(Some(LOG_FLUSH_TIMEOUT));
         self.send(Message::Exiting)?;
         Ok(())
     }
@@ -0,0 +1,191 @@
+//! Filters and merges log files written by a `FileLogger` with `LogfmtFormatter`, such as the
+//! host's and the payload's logs. Files written with the default text format can't be read.
+//!
+//! `logq --level warn --origin payload host.log payload.log` prints every warning and error the
+//! payload logged, interleaved by time.
+
+use std::{path::PathBuf, process::ExitCode};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use logger::{
+    formatters::{LogFormatter, json::JsonFormatter, logfmt::LogfmtFormatter, text::TextFormatter},
+    loggers::ring::LogQuery,
+    reader::{LogReader, merge},
+};
+use regex::Regex;
+
+const USAGE: &str = "Usage: logq [options] <file>...
+
+Prints the messages in every file that match all the given options, merged by time.
+
+Options:
+  -l, --level <severity>    Only messages at least as severe as this
+      --since <time>        Only messages logged at or after this time
+      --until <time>        Only messages logged at or before this time
+  -o, --origin <component>  Only messages from this component, can be repeated
+  -e, --regex <pattern>     Only messages whose content matches this pattern
+  -f, --format <format>     Output as text (default), logfmt or json
+  -h, --help                Print this help
+
+Times are RFC 3339, or `YYYY-MM-DD[ HH:MM[:SS]]` in local time.";
+
+struct Args {
+    query: LogQuery,
+    formatter: Box<dyn LogFormatter>,
+    files: Vec<PathBuf>,
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| format!("Invalid time `{}`", value))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("`{}` doesn't exist in local time", value))
+}
+
+/// Returns `None` if help was asked for
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut query = LogQuery::new();
+    let mut formatter: Box<dyn LogFormatter> = Box::new(TextFormatter::new());
+    let mut files = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for `{}`", arg));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-l" | "--level" => {
+                query = query.with_severity(value()?.parse().map_err(|e| format!("{}", e))?);
+            }
+            "--since" => query = query.with_since(parse_time(&value()?)?),
+            "--until" => query = query.with_until(parse_time(&value()?)?),
+            "-o" | "--origin" => query = query.with_origin(value()?),
+            "-e" | "--regex" => {
+                query = query.with_pattern(Regex::new(&value()?).map_err(|e| e.to_string())?);
+            }
+            "-f" | "--format" => {
+                formatter = match value()?.as_str() {
+                    "text" => Box::new(TextFormatter::new()),
+                    "logfmt" => Box::new(LogfmtFormatter::new()),
+                    "json" => Box::new(JsonFormatter::new()),
+                    other => return Err(format!("Unknown format `{}`", other)),
+                }
+            }
+            option if option.starts_with('-') => return Err(format!("Unknown option `{}`", option)),
+            file => files.push(PathBuf::from(file)),
+        }
+    }
+    if files.is_empty() {
+        return Err(String::from("No log files given"));
+    }
+    Ok(Some(Args {
+        query,
+        formatter,
+        files,
+    }))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let mut readers = Vec::new();
+    for path in &args.files {
+        match LogReader::open(path) {
+            Ok(reader) => readers.push(reader.map(move |res| res.map_err(|e| format!("{}: {}", path.display(), e)))),
+            Err(e) => {
+                eprintln!("Failed to open {}. {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut failed = false;
+    for res in merge(readers) {
+        match res {
+            Ok(message) if args.query.matches(&message) => println!("{}", args.formatter.format(&message)),
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use logger::{LogMessage, LogOrigin, severity::LogSeverity};
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_times() {
+        let utc = parse_time("2025-03-01T12:30:00+00:00").unwrap();
+        assert_eq!(utc, chrono::Utc.with_ymd_and_hms(2025, 3, 1, 12, 30, 0).unwrap());
+        let local = Local.with_ymd_and_hms(2025, 3, 1, 12, 30, 0).unwrap();
+        assert_eq!(parse_time("2025-03-01 12:30").unwrap(), local);
+        assert_eq!(parse_time("2025-03-01 12:30:00").unwrap(), local);
+        assert_eq!(parse_time("2025-03-01").unwrap(), Local.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap());
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn parses_options_and_files() {
+        let options = ["--level", "warn", "-o", "payload", "-e", "walk", "-f", "logfmt"];
+        let parsed = args(&[&options[..], &["host.log", "dll.log"]].concat()).unwrap().unwrap();
+        assert_eq!(parsed.files, [PathBuf::from("host.log"), PathBuf::from("dll.log")]);
+        let payload = LogOrigin { process_id: 7, component: String::from("payload") };
+        let matching = LogMessage::new(LogSeverity::Error, "Memory walk failed").with_origin(payload.clone());
+        assert!(parsed.query.matches(&matching));
+        assert!(!parsed.query.matches(&LogMessage::new(LogSeverity::Info, "walk").with_origin(payload)));
+        let host = LogMessage::new(LogSeverity::Error, "walk").with_origin(LogOrigin::new("host"));
+        assert!(!parsed.query.matches(&host));
+        assert!(parsed.formatter.format(&matching).contains("level=error"));
+
+        let parsed = args(&["--since", "2025-03-01", "--until", "2025-03-02", "host.log"]).unwrap().unwrap();
+        let mut message = LogMessage::new(LogSeverity::Info, "in range");
+        message.time = Local.with_ymd_and_hms(2025, 3, 1, 18, 0, 0).unwrap();
+        assert!(parsed.query.matches(&message));
+        message.time = Local.with_ymd_and_hms(2025, 3, 3, 0, 0, 0).unwrap();
+        assert!(!parsed.query.matches(&message));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(args(&["-h", "host.log"]).unwrap().is_none());
+        assert!(args(&[]).is_err_and(|e| e.contains("No log files")));
+        assert!(args(&["host.log", "--level"]).is_err_and(|e| e.contains("Missing value for `--level`")));
+        assert!(args(&["-f", "xml", "host.log"]).is_err_and(|e| e.contains("Unknown format")));
+        assert!(args(&["--verbose", "host.log"]).is_err_and(|e| e.contains("Unknown option")));
+        assert!(args(&["-l", "loud", "host.log"]).is_err());
+        assert!(args(&["-e", "(", "host.log"]).is_err());
+    }
+}
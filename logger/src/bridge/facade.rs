@@ -0,0 +1,165 @@
+use ::log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{LogManager, LogMessage, LogWorker, severity::LogSeverity};
+
+impl From<Level> for LogSeverity {
+    fn from(value: Level) -> Self {
+        match value {
+            Level::Error => LogSeverity::Error,
+            Level::Warn => LogSeverity::Warning,
+            Level::Info => LogSeverity::Info,
+            Level::Debug => LogSeverity::Debug,
+            Level::Trace => LogSeverity::Verbose,
+        }
+    }
+}
+
+impl From<LogSeverity> for LevelFilter {
+    fn from(value: LogSeverity) -> Self {
+        match value {
+            LogSeverity::Error => LevelFilter::Error,
+            LogSeverity::Warning => LevelFilter::Warn,
+            LogSeverity::Info => LevelFilter::Info,
+            LogSeverity::Debug => LevelFilter::Debug,
+            LogSeverity::Verbose => LevelFilter::Trace,
+        }
+    }
+}
+
+/// A [`log::Log`] implementation that forwards records into a [`LogManager`]'s queue
+pub struct LogBridge {
+    worker: LogWorker,
+    level: LevelFilter,
+}
+
+impl LogBridge {
+    pub fn new(worker: LogWorker, level: LogSeverity) -> Self {
+        Self {
+            worker,
+            level: level.into(),
+        }
+    }
+
+    /// Installs the bridge as the global `log` logger.
+    ///
+    /// This can only succeed once per process (or per DLL).
+    pub fn install(self) -> Result<(), SetLoggerError> {
+        let level = self.level;
+        ::log::set_boxed_logger(Box::new(self))?;
+        ::log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for LogBridge {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut message = LogMessage::new(record.level().into(), record.args().to_string())
+            .with_target(record.target());
+        if let (Some(file), Some(line)) = (record.file(), record.line()) {
+            message = message.with_location(file, line);
+        }
+        let _ = self.worker.log(message);
+    }
+
+    fn flush(&self) {
+        let _ = self.worker.flush(None);
+    }
+}
+
+impl LogManager {
+    /// Routes everything logged through the `log` facade at `level` or above into this manager
+    pub fn install_log_bridge(&self, level: LogSeverity) -> Result<(), SetLoggerError> {
+        LogBridge::new(self.get_log_worker(), level).install()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc, OnceLock,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        LogResult, Logger, OverflowPolicy, SourceLocation,
+        loggers::ring::{LogQuery, RingBufferLogger},
+    };
+
+    #[test]
+    fn maps_levels() {
+        assert_eq!(LogSeverity::from(Level::Warn), LogSeverity::Warning);
+        assert_eq!(LogSeverity::from(Level::Trace), LogSeverity::Verbose);
+        assert_eq!(LevelFilter::from(LogSeverity::Warning), LevelFilter::Warn);
+        assert_eq!(LevelFilter::from(LogSeverity::Verbose), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn forwards_records_at_or_above_its_level() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let manager = LogManager::new(ring);
+        let bridge = LogBridge::new(manager.get_log_worker(), LogSeverity::Debug);
+        bridge.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("ipc_channel::platform")
+                .file(Some("src/platform.rs"))
+                .line(Some(12))
+                .args(format_args!("Pipe closed after {} bytes", 64))
+                .build(),
+        );
+        bridge.log(&Record::builder().level(Level::Trace).args(format_args!("Too verbose")).build());
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+
+        let logged = handle.query(&LogQuery::new());
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].severity, LogSeverity::Warning);
+        assert_eq!(logged[0].content, "Pipe closed after 64 bytes");
+        assert_eq!(logged[0].target.as_deref(), Some("ipc_channel::platform"));
+        assert_eq!(
+            logged[0].location,
+            Some(SourceLocation { file: String::from("src/platform.rs"), line: 12 })
+        );
+    }
+
+    /// Logs through the `log` facade from inside `log`, like a sink built on a crate that logs
+    struct ChattyLogger {
+        bridge: Arc<OnceLock<LogBridge>>,
+        chatted: AtomicBool,
+    }
+
+    impl Logger for ChattyLogger {
+        fn log(&mut self, _message: &LogMessage) -> LogResult {
+            if let Some(bridge) = self.bridge.get()
+                && !self.chatted.swap(true, Ordering::Relaxed)
+            {
+                for _ in 0..3 {
+                    bridge.log(&Record::builder().level(Level::Info).args(format_args!("Sent")).build());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logging_thread_does_not_wait_for_room() {
+        let bridge = Arc::new(OnceLock::new());
+        let logger = ChattyLogger { bridge: bridge.clone(), chatted: AtomicBool::new(false) };
+        let manager = LogManager::bounded(logger, 1, OverflowPolicy::Block);
+        assert!(bridge.set(LogBridge::new(manager.get_log_worker(), LogSeverity::Info)).is_ok());
+        assert!(manager.log(LogMessage::new(LogSeverity::Info, "Connecting")));
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+        assert!(manager.dropped_messages() > 0);
+    }
+}
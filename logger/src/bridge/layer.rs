@@ -0,0 +1,113 @@
+use std::fmt::Debug;
+
+use tracing_core::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context};
+
+use crate::{LogManager, LogMessage, LogWorker, severity::LogSeverity};
+
+impl From<&Level> for LogSeverity {
+    fn from(value: &Level) -> Self {
+        match *value {
+            Level::ERROR => LogSeverity::Error,
+            Level::WARN => LogSeverity::Warning,
+            Level::INFO => LogSeverity::Info,
+            Level::DEBUG => LogSeverity::Debug,
+            _ => LogSeverity::Verbose,
+        }
+    }
+}
+
+/// A `tracing-subscriber` layer that forwards events into a [`LogManager`]'s queue.
+///
+/// The event's `message` becomes the content, every other field is attached as a message field.
+pub struct TracingLayer {
+    worker: LogWorker,
+    level: LogSeverity,
+}
+
+impl TracingLayer {
+    pub fn new(worker: LogWorker, level: LogSeverity) -> Self {
+        Self { worker, level }
+    }
+}
+
+struct MessageVisitor {
+    message: LogMessage,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.content = value.to_string();
+        } else {
+            self.message.fields.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message.content = format!("{:?}", value);
+        } else {
+            self.message.fields.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for TracingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let severity = LogSeverity::from(metadata.level());
+        if severity > self.level {
+            return;
+        }
+        let mut message = LogMessage::new(severity, String::new()).with_target(metadata.target());
+        if let (Some(file), Some(line)) = (metadata.file(), metadata.line()) {
+            message = message.with_location(file, line);
+        }
+        let mut visitor = MessageVisitor { message };
+        event.record(&mut visitor);
+        let _ = self.worker.log(visitor.message);
+    }
+}
+
+impl LogManager {
+    /// A layer that can be added to a `tracing` subscriber to log events at `level` or above
+    pub fn tracing_layer(&self, level: LogSeverity) -> TracingLayer {
+        TracingLayer::new(self.get_log_worker(), level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::loggers::ring::{LogQuery, RingBufferLogger};
+
+    #[test]
+    fn forwards_events_with_their_fields() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let manager = LogManager::new(ring);
+        let subscriber = tracing_subscriber::registry().with(manager.tracing_layer(LogSeverity::Debug));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "client::walk", region = 3, size = "4KiB", "Skipped {} pages", 2);
+            tracing::trace!("Too verbose");
+        });
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+
+        let logged = handle.query(&LogQuery::new());
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].severity, LogSeverity::Warning);
+        assert_eq!(logged[0].content, "Skipped 2 pages");
+        assert_eq!(logged[0].target.as_deref(), Some("client::walk"));
+        assert_eq!(logged[0].location.as_ref().map(|loc| loc.file.as_str()), Some(file!()));
+        assert_eq!(logged[0].field("region"), Some("3"));
+        assert_eq!(logged[0].field("size"), Some("4KiB"));
+    }
+}
@@ -0,0 +1,14 @@
+//! Adapters that feed messages from other logging ecosystems into a [`LogManager`](crate::LogManager).
+//!
+//! Both only enqueue through a [`LogWorker`](crate::LogWorker), so the messages go through the same
+//! queue and logging thread as everything else.
+
+#[cfg(feature = "log")]
+mod facade;
+#[cfg(feature = "tracing")]
+mod layer;
+
+#[cfg(feature = "log")]
+pub use facade::LogBridge;
+#[cfg(feature = "tracing")]
+pub use layer::TracingLayer;
@@ -0,0 +1,608 @@
+//! Logger pipelines described in JSON instead of built in code.
+//!
+//! Every sink has a `type`, wrappers such as `filter` take the sink they pass messages on to:
+//!
+//! ```json
+//! {
+//!     "origin": "host",
+//!     "queue": { "capacity": 4096, "overflow": "drop_oldest" },
+//!     "sink": {
+//!         "type": "filter",
+//!         "directives": "client::master=info,*=debug",
+//!         "env": "R6_LOG",
+//!         "sink": {
+//!             "type": "multi",
+//!             "sinks": [
+//!                 { "type": "console", "formatter": { "type": "text", "origin_colours": true } },
+//!                 {
+//!                     "type": "file",
+//!                     "name": "file",
+//!                     "path": "logs/host.log",
+//!                     "conflict": "rename_old",
+//!                     "rotation": { "max_size": 16777216, "keep": 5 }
+//!                 }
+//!             ]
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Loggers that only exist in code, like an IPC connection, are declared as `external` sinks and
+//! passed to [`build_with`] by name.
+
+use std::{
+    collections::HashMap,
+    net::ToSocketAddrs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    LogManager, Logger, OverflowPolicy,
+    formatters::{LogFormatter, json::JsonFormatter, logfmt::LogfmtFormatter, text::TextFormatter},
+    loggers::{
+        console::ConsoleLogger,
+        dedup::DuplicateFilter,
+        file::{FileConflictBehavior, FileLogger, RotationInterval, RotationNaming, RotationPolicy},
+        filter::{FilterDirectives, FilterParseError, LogFilter},
+        multi::{MultiLogger, RetryPolicy},
+        null::NullLogger,
+        origin::OriginFilter,
+        rate_limit::RateLimiter,
+    },
+    net::{FrameFormat, NetworkLogger, NetworkTarget},
+    severity::{LogSeverity, ParseSeverityError},
+};
+
+/// Loggers built in code, referenced from a config by `{ "type": "external", "name": ... }`
+pub type ExternalSinks = HashMap<String, Box<dyn Logger + Send>>;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read log config {path}. {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("Invalid log config. {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Invalid filter at {at}. {source}")]
+    Filter { at: String, source: FilterParseError },
+    /// The filter came from the environment variable named by the sink's `env`
+    #[error("Invalid filter in ${var}, which overrides the directives at {at}. {source}")]
+    EnvFilter { at: String, var: String, source: FilterParseError },
+    #[error("Invalid severity at {at}. {source}")]
+    Severity { at: String, source: ParseSeverityError },
+    #[error("Failed to open log file {path} at {at}. {source}")]
+    File { at: String, path: PathBuf, source: std::io::Error },
+    #[error("Invalid address `{address}` at {at}")]
+    Address { at: String, address: String },
+    #[error("Unknown or already used external sink `{name}` at {at}")]
+    External { at: String, name: String },
+    #[error("Origin sink at {at} lists no components")]
+    NoComponents { at: String },
+}
+
+/// The whole pipeline: the root sink plus how the [`LogManager`] in front of it behaves
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    pub sink: SinkConfig,
+    /// Component stamped on every message logged through the manager
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// Bounds the manager's queue, unbounded if missing
+    #[serde(default)]
+    pub queue: Option<QueueConfig>,
+}
+
+impl FromStr for LogConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl LogConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            })?
+            .parse()
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    #[serde(default)]
+    pub overflow: OverflowConfig,
+    /// Queue through a lock-free ring buffer, see [`LogManager::lock_free`]
+    #[serde(default)]
+    pub lock_free: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowConfig {
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    Console {
+        #[serde(default)]
+        formatter: Option<FormatterConfig>,
+    },
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        conflict: ConflictConfig,
+        #[serde(default)]
+        rotation: Option<RotationConfig>,
+        #[serde(default)]
+        formatter: Option<FormatterConfig>,
+    },
+    Network {
+        protocol: ProtocolConfig,
+        /// `host:port`, or a socket path for `unix`
+        address: String,
+        #[serde(default)]
+        format: FrameConfig,
+    },
+    Null,
+    Multi {
+        sinks: Vec<NamedSinkConfig>,
+        #[serde(default)]
+        retry: Option<RetryConfig>,
+    },
+    /// Severity directives such as `windows_fns=verbose,*=info`, see [`FilterDirectives`]
+    Filter {
+        directives: String,
+        /// Environment variable that replaces `directives` when it is set
+        #[serde(default)]
+        env: Option<String>,
+        sink: Box<SinkConfig>,
+    },
+    Dedup {
+        /// Collapse messages that only differ by their fields
+        #[serde(default)]
+        ignore_fields: bool,
+        sink: Box<SinkConfig>,
+    },
+    RateLimit {
+        limits: Vec<RateLimitConfig>,
+        sink: Box<SinkConfig>,
+    },
+    Origin {
+        components: Vec<String>,
+        #[serde(default)]
+        exclude: bool,
+        sink: Box<SinkConfig>,
+    },
+    External {
+        name: String,
+    },
+}
+
+/// A [`MultiLogger`] entry, the name shows up in its health reports
+#[derive(Deserialize, Clone, Debug)]
+pub struct NamedSinkConfig {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub sink: SinkConfig,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FormatterConfig {
+    Text {
+        #[serde(default)]
+        time_format: Option<String>,
+        #[serde(default)]
+        origin_colours: bool,
+    },
+    Json,
+    Logfmt,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictConfig {
+    AppendNumber,
+    #[default]
+    Append,
+    Error,
+    Overwrite,
+    RenameOld,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RotationConfig {
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    #[serde(default)]
+    pub interval: Option<IntervalConfig>,
+    #[serde(default)]
+    pub keep: Option<usize>,
+    #[serde(default)]
+    pub naming: Option<NamingConfig>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalConfig {
+    Hourly,
+    Daily,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NamingConfig {
+    RenameOld,
+    AppendNumber,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolConfig {
+    Tcp,
+    Udp,
+    #[cfg(unix)]
+    Unix,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameConfig {
+    #[default]
+    Json,
+    Bincode,
+}
+
+/// Overrides for [`RetryPolicy`], missing fields keep their defaults
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    #[serde(default)]
+    pub retries: Option<u32>,
+    #[serde(default)]
+    pub initial_backoff_ms: Option<u64>,
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+    /// `0` never disables a failing logger
+    #[serde(default)]
+    pub max_failures: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub severity: String,
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+/// Builds the [`LogManager`] described by `config`
+pub fn build(config: &LogConfig) -> Result<LogManager, ConfigError> {
+    build_with(config, ExternalSinks::new())
+}
+
+/// Builds the [`LogManager`] described by `config`, each `external` sink taking its logger from
+/// `external`
+pub fn build_with(config: &LogConfig, mut external: ExternalSinks) -> Result<LogManager, ConfigError> {
+    let logger = build_sink(&config.sink, "sink", &mut external)?;
+    let manager = match &config.queue {
+        Some(queue) => {
+            let policy = match queue.overflow {
+                OverflowConfig::Block => OverflowPolicy::Block,
+                OverflowConfig::DropOldest => OverflowPolicy::DropOldest,
+                OverflowConfig::DropNewest => OverflowPolicy::DropNewest,
+            };
+            if queue.lock_free {
+                LogManager::lock_free(logger, queue.capacity, policy)
+            } else {
+                LogManager::bounded(logger, queue.capacity, policy)
+            }
+        }
+        None => LogManager::new(logger),
+    };
+    Ok(match &config.origin {
+        Some(component) => manager.with_origin(component.clone()),
+        None => manager,
+    })
+}
+
+/// Reads the config at `path` and builds it
+pub fn load(path: impl AsRef<Path>) -> Result<LogManager, ConfigError> {
+    build(&LogConfig::load(path)?)
+}
+
+/// Builds one sink, `at` is its position in the tree for error messages
+fn build_sink(
+    config: &SinkConfig,
+    at: &str,
+    external: &mut ExternalSinks,
+) -> Result<Box<dyn Logger + Send>, ConfigError> {
+    let at_sink = format!("{}.sink", at);
+    Ok(match config {
+        SinkConfig::Console { formatter } => match formatter {
+            Some(formatter) => Box::new(ConsoleLogger::new().with_formatter(build_formatter(formatter))),
+            None => Box::new(ConsoleLogger::new()),
+        },
+        SinkConfig::File {
+            path,
+            conflict,
+            rotation,
+            formatter,
+        } => {
+            let behavior = match conflict {
+                ConflictConfig::AppendNumber => FileConflictBehavior::AppendNumber,
+                ConflictConfig::Append => FileConflictBehavior::Append,
+                ConflictConfig::Error => FileConflictBehavior::Error,
+                ConflictConfig::Overwrite => FileConflictBehavior::Overwrite,
+                ConflictConfig::RenameOld => FileConflictBehavior::RenameOld,
+            };
+            let policy = rotation.as_ref().map(build_rotation).unwrap_or_default();
+            let logger = FileLogger::with_rotation(path.clone(), behavior, policy).map_err(|source| {
+                ConfigError::File {
+                    at: at.to_string(),
+                    path: path.clone(),
+                    source,
+                }
+            })?;
+            match formatter {
+                Some(formatter) => Box::new(logger.with_formatter(build_formatter(formatter))),
+                None => Box::new(logger),
+            }
+        }
+        SinkConfig::Network {
+            protocol,
+            address,
+            format,
+        } => {
+            let format = match format {
+                FrameConfig::Json => FrameFormat::Json,
+                FrameConfig::Bincode => FrameFormat::Bincode,
+            };
+            let invalid = || ConfigError::Address {
+                at: at.to_string(),
+                address: address.clone(),
+            };
+            let resolve = || {
+                address
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .ok_or_else(invalid)
+            };
+            let target = match protocol {
+                ProtocolConfig::Tcp => NetworkTarget::Tcp(resolve()?),
+                ProtocolConfig::Udp => NetworkTarget::Udp(resolve()?),
+                #[cfg(unix)]
+                ProtocolConfig::Unix => NetworkTarget::Unix(PathBuf::from(address)),
+            };
+            Box::new(NetworkLogger::new(target, format))
+        }
+        SinkConfig::Null => Box::new(NullLogger::new()),
+        SinkConfig::Multi { sinks, retry } => {
+            let mut multi = MultiLogger::new();
+            if let Some(retry) = retry {
+                multi = multi.with_retry_policy(build_retry(retry));
+            }
+            for (index, sink) in sinks.iter().enumerate() {
+                let logger = build_sink(&sink.sink, &format!("{}.sinks[{}]", at, index), external)?;
+                multi = match &sink.name {
+                    Some(name) => multi.with_named_logger(name.clone(), logger),
+                    None => multi.with_logger(logger),
+                };
+            }
+            Box::new(multi)
+        }
+        SinkConfig::Filter { directives, env, sink } => {
+            let overridden = env.as_ref().and_then(|var| Some((var, std::env::var(var).ok()?)));
+            let directives: FilterDirectives = match overridden {
+                Some((var, value)) => value.parse().map_err(|source| ConfigError::EnvFilter {
+                    at: at.to_string(),
+                    var: var.clone(),
+                    source,
+                })?,
+                None => directives.parse().map_err(|source| ConfigError::Filter {
+                    at: at.to_string(),
+                    source,
+                })?,
+            };
+            Box::new(LogFilter::with_directives(directives, build_sink(sink, &at_sink, external)?))
+        }
+        SinkConfig::Dedup { ignore_fields, sink } => {
+            let dedup = DuplicateFilter::new(build_sink(sink, &at_sink, external)?);
+            match ignore_fields {
+                true => Box::new(dedup.ignoring_fields()),
+                false => Box::new(dedup),
+            }
+        }
+        SinkConfig::RateLimit { limits, sink } => {
+            let mut limiter = RateLimiter::new(build_sink(sink, &at_sink, external)?);
+            for (index, limit) in limits.iter().enumerate() {
+                let severity: LogSeverity = limit.severity.parse().map_err(|source| ConfigError::Severity {
+                    at: format!("{}.limits[{}]", at, index),
+                    source,
+                })?;
+                limiter = limiter.with_limit(severity, limit.per_second, limit.burst);
+            }
+            Box::new(limiter)
+        }
+        SinkConfig::Origin {
+            components,
+            exclude,
+            sink,
+        } => {
+            let mut components = components.iter();
+            let first = components
+                .next()
+                .cloned()
+                .ok_or_else(|| ConfigError::NoComponents { at: at.to_string() })?;
+            let logger = build_sink(sink, &at_sink, external)?;
+            let filter = match exclude {
+                true => OriginFilter::excluding(first, logger),
+                false => OriginFilter::new(first, logger),
+            };
+            Box::new(components.fold(filter, |filter, c| filter.with_component(c.clone())))
+        }
+        SinkConfig::External { name } => external.remove(name).ok_or_else(|| ConfigError::External {
+            at: at.to_string(),
+            name: name.clone(),
+        })?,
+    })
+}
+
+fn build_formatter(config: &FormatterConfig) -> Box<dyn LogFormatter + Send> {
+    match config {
+        FormatterConfig::Text {
+            time_format,
+            origin_colours,
+        } => {
+            let mut formatter = TextFormatter::new();
+            if let Some(time_format) = time_format {
+                formatter = formatter.with_time_format(time_format.clone());
+            }
+            if *origin_colours {
+                formatter = formatter.with_origin_colours();
+            }
+            Box::new(formatter)
+        }
+        FormatterConfig::Json => Box::new(JsonFormatter::new()),
+        FormatterConfig::Logfmt => Box::new(LogfmtFormatter::new()),
+    }
+}
+
+fn build_rotation(config: &RotationConfig) -> RotationPolicy {
+    let mut policy = RotationPolicy::new();
+    if let Some(max_size) = config.max_size {
+        policy = policy.with_max_size(max_size);
+    }
+    if let Some(interval) = config.interval {
+        policy = policy.with_interval(match interval {
+            IntervalConfig::Hourly => RotationInterval::Hourly,
+            IntervalConfig::Daily => RotationInterval::Daily,
+        });
+    }
+    if let Some(keep) = config.keep {
+        policy = policy.with_keep(keep);
+    }
+    if let Some(naming) = config.naming {
+        policy = policy.with_naming(match naming {
+            NamingConfig::RenameOld => RotationNaming::RenameOld,
+            NamingConfig::AppendNumber => RotationNaming::AppendNumber,
+        });
+    }
+    policy
+}
+
+fn build_retry(config: &RetryConfig) -> RetryPolicy {
+    let default = RetryPolicy::default();
+    RetryPolicy {
+        retries: config.retries.unwrap_or(default.retries),
+        initial_backoff: config
+            .initial_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(default.initial_backoff),
+        max_backoff: config
+            .max_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(default.max_backoff),
+        max_failures: match config.max_failures {
+            Some(0) => None,
+            Some(max) => Some(max),
+            None => default.max_failures,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        LogMessage,
+        loggers::ring::{LogQuery, RingBufferLogger},
+    };
+
+    #[test]
+    fn builds_pipeline_with_external_sink() {
+        let config: LogConfig = r#"{
+            "origin": "test",
+            "queue": { "capacity": 16, "overflow": "drop_newest", "lock_free": true },
+            "sink": {
+                "type": "filter",
+                "directives": "noisy=error,*=info",
+                "sink": {
+                    "type": "multi",
+                    "sinks": [
+                        { "type": "null", "name": "discard" },
+                        { "type": "origin", "components": ["test"], "sink": { "type": "external", "name": "ring" } }
+                    ]
+                }
+            }
+        }"#
+        .parse()
+        .unwrap();
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let external = ExternalSinks::from([(String::from("ring"), Box::new(ring) as Box<dyn Logger + Send>)]);
+        let manager = build_with(&config, external).unwrap();
+        manager.log(LogMessage::new(LogSeverity::Info, "kept"));
+        manager.log(LogMessage::new(LogSeverity::Debug, "filtered"));
+        manager.log(LogMessage::new(LogSeverity::Warning, "noisy").with_target("noisy::module"));
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+        let messages = handle.query(&LogQuery::new());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].component(), Some("test"));
+    }
+
+    #[test]
+    fn errors_name_their_location() {
+        let config: LogConfig =
+            r#"{ "sink": { "type": "dedup", "sink": { "type": "filter", "directives": "=info", "sink": { "type": "null" } } } }"#
+                .parse()
+                .unwrap();
+        let error = build(&config).err().unwrap();
+        assert!(matches!(&error, ConfigError::Filter { at, .. } if at == "sink.sink"));
+
+        let error = r#"{ "sink": { "type": "console", "colour": true } }"#.parse::<LogConfig>().unwrap_err();
+        assert!(error.to_string().contains("colour"));
+        let error = build(&r#"{ "sink": { "type": "external", "name": "ipc" } }"#.parse().unwrap()).err().unwrap();
+        assert!(matches!(error, ConfigError::External { .. }));
+        let config = r#"{ "sink": { "type": "origin", "components": [], "sink": { "type": "null" } } }"#;
+        let error = build(&config.parse().unwrap()).err().unwrap();
+        assert!(matches!(&error, ConfigError::NoComponents { at } if at == "sink"));
+    }
+
+    #[test]
+    fn filter_errors_name_the_environment_variable() {
+        let var = "LOGGER_CONFIG_TEST_FILTER";
+        // Nothing else reads this variable
+        unsafe { std::env::set_var(var, "=info") };
+        let config = format!(
+            r#"{{ "sink": {{ "type": "filter", "directives": "info", "env": "{}", "sink": {{ "type": "null" }} }} }}"#,
+            var
+        );
+        let error = build(&config.parse().unwrap()).err().unwrap();
+        assert!(matches!(&error, ConfigError::EnvFilter { var: name, .. } if name == var));
+        assert!(error.to_string().contains(var));
+        unsafe { std::env::remove_var(var) };
+    }
+}
@@ -0,0 +1,140 @@
+//! A synchronous logging path for when the queued [`LogManager`](crate::LogManager) can't be relied
+//! on, such as while panicking or right before the payload unloads.
+//!
+//! Sinks are opened up front with [`add_sink`] so nothing has to be set up once things have already
+//! gone wrong. Normal logging should keep going through a [`LogWorker`](crate::LogWorker).
+
+use std::{
+    backtrace::Backtrace,
+    sync::{Mutex, MutexGuard, TryLockError},
+};
+
+use crate::{severity::LogSeverity, LogError, LogMessage, LogResult, Logger};
+
+static SINKS: Mutex<Vec<Box<dyn Logger + Send>>> = Mutex::new(Vec::new());
+
+/// Locks the sinks even if a previous panic poisoned them. Never blocks, so a panic raised by a sink
+/// while logging can't deadlock the panic hook.
+fn sinks() -> Option<MutexGuard<'static, Vec<Box<dyn Logger + Send>>>> {
+    match SINKS.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poison)) => Some(poison.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Registers a sink that emergency messages are written to
+pub fn add_sink<L: Logger + Send + 'static>(logger: L) {
+    let mut guard = match SINKS.lock() {
+        Ok(guard) => guard,
+        Err(poison) => poison.into_inner(),
+    };
+    guard.push(Box::new(logger));
+}
+
+/// Removes every registered sink
+pub fn clear_sinks() {
+    if let Some(mut sinks) = sinks() {
+        sinks.clear();
+    }
+}
+
+/// Writes `message` to every emergency sink and flushes them before returning.
+///
+/// Falls back to stderr if no sinks are registered or they are in use by another thread.
+pub fn log(message: &LogMessage) -> LogResult {
+    let Some(mut sinks) = sinks().filter(|sinks| !sinks.is_empty()) else {
+        eprintln!("({}) {} : {}", message.time.format("%Y-%m-%d %H:%M:%S"), message.severity, message.content);
+        return Ok(());
+    };
+    let mut failed = 0;
+    for sink in sinks.iter_mut() {
+        if sink.log(message).and_then(|_| sink.flush()).is_err() {
+            failed += 1;
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        failed => Err(LogError::PartialFailure { failed, total: sinks.len() }),
+    }
+}
+
+/// Installs a panic hook that records the panic message, location, thread and a backtrace through
+/// the emergency path, then runs whichever hook was installed before it
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| String::from("Box<dyn Any>"));
+        let thread = std::thread::current();
+        let mut message = LogMessage::new(LogSeverity::Error, format!("Panic: {}", payload))
+            .with_target(module_path!())
+            .with_field("thread", thread.name().unwrap_or("<unnamed>"))
+            .with_field("backtrace", Backtrace::force_capture());
+        if let Some(location) = info.location() {
+            message = message.with_location(location.file(), location.line());
+        }
+        let _ = log(&message);
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        LogManager,
+        loggers::ring::{LogQuery, RingBufferLogger},
+    };
+
+    /// Stuck in `log` until the gate is released, like a sink waiting on a dead pipe
+    struct GatedLogger {
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl Logger for GatedLogger {
+        fn log(&mut self, _message: &LogMessage) -> LogResult {
+            let _open = self.gate.lock();
+            Ok(())
+        }
+    }
+
+    // One test, since the sinks and the panic hook are global
+    #[test]
+    fn writes_synchronously_while_the_worker_is_stuck() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        add_sink(ring);
+        let gate = Arc::new(Mutex::new(()));
+        let closed = gate.lock().unwrap();
+        let manager = LogManager::new(GatedLogger { gate: gate.clone() });
+        assert!(manager.log(LogMessage::new(LogSeverity::Info, "Walking")));
+        assert!(!manager.flush(Some(Duration::from_millis(50))));
+
+        assert!(log(&LogMessage::new(LogSeverity::Error, "Unloading")).is_ok());
+        assert_eq!(handle.query(&LogQuery::new().with_text("Unloading")).len(), 1);
+
+        install_panic_hook();
+        assert!(std::thread::spawn(|| panic!("walk failed")).join().is_err());
+        // Back to the default hook
+        let _ = std::panic::take_hook();
+        let panics = handle.query(&LogQuery::new().with_text("Panic: walk failed"));
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].location.as_ref().map(|loc| loc.file.as_str()), Some(file!()));
+        assert!(panics[0].field("thread").is_some());
+        assert!(panics[0].field("backtrace").is_some());
+
+        clear_sinks();
+        drop(closed);
+        drop(manager);
+    }
+}
@@ -0,0 +1,30 @@
+use thiserror::Error;
+use thread_safe_utils::queue::ThreadSafeQueueError;
+
+#[derive(Error, Debug)]
+pub enum LogError {
+    #[error("An IO error occured while logging. {0}")]
+    IO(#[from] std::io::Error),
+    #[error("A structured exception occured while logging. {0}")]
+    Exception(String),
+    #[error("The logger's mutex was poisoned")]
+    MutexPoisoned,
+    #[error("The log destination was closed")]
+    Closed,
+    #[error("The logger is disabled after repeated failures")]
+    Disabled,
+    /// The destination is working but couldn't take this message right now, such as because its
+    /// queue is full. [`MultiLogger`](crate::loggers::multi::MultiLogger) doesn't count it as a
+    /// failure.
+    #[error("The log destination dropped the message")]
+    Dropped,
+    /// The destination's queue refused the message, because it is full, closed or cancelled
+    #[error("The log destination couldn't queue the message. {0}")]
+    Queue(#[from] ThreadSafeQueueError),
+    #[error("Failed to encode or decode a log message. {0}")]
+    Codec(String),
+    #[error("{failed} of {total} loggers failed")]
+    PartialFailure { failed: usize, total: usize },
+}
+
+pub type LogResult = Result<(), LogError>;
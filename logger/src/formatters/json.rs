@@ -0,0 +1,45 @@
+use crate::LogMessage;
+
+use super::LogFormatter;
+
+/// One JSON object per line (JSON Lines), using the serde representation of [`LogMessage`]
+#[derive(Default)]
+pub struct JsonFormatter {}
+
+impl JsonFormatter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl LogFormatter for JsonFormatter {
+    fn format(&self, message: &LogMessage) -> String {
+        // LogMessage only holds strings, a timestamp and a unit enum, so this can't fail
+        serde_json::to_string(message).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogOrigin, severity::LogSeverity};
+
+    #[test]
+    fn one_object_per_line() {
+        let message = LogMessage::new(LogSeverity::Error, "Walk failed\nat region 3")
+            .with_target("client::slave")
+            .with_location("src/slave/mod.rs", 42)
+            .with_field("region", 3)
+            .with_origin(LogOrigin { process_id: 1234, component: String::from("payload") });
+        let line = JsonFormatter::new().format(&message);
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["content"], "Walk failed\nat region 3");
+        assert_eq!(value["target"], "client::slave");
+        assert_eq!(value["location"]["file"], "src/slave/mod.rs");
+        assert_eq!(value["location"]["line"], 42);
+        assert_eq!(value["fields"]["region"], "3");
+        assert_eq!(value["origin"]["component"], "payload");
+        assert_eq!(value["origin"]["process_id"], 1234);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+use crate::LogMessage;
+
+use super::LogFormatter;
+
+/// `key=value` pairs as understood by logfmt tooling
+#[derive(Default)]
+pub struct LogfmtFormatter {}
+
+impl LogfmtFormatter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+/// Appends `key=value`, quoting and escaping the value if needed. Characters that can't appear in
+/// an unquoted key are replaced with `_` so the line can be split back into its pairs.
+pub(crate) fn write_pair(out: &mut String, key: &str, value: &str) {
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    let key = key.replace(|c: char| c.is_whitespace() || c == '=' || c == '"' || c.is_control(), "_");
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '=' || c == '"' || c == '\\' || c.is_control());
+    if needs_quotes {
+        let _ = write!(out, "{}=\"", key);
+        for c in value.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    } else {
+        let _ = write!(out, "{}={}", key, value);
+    }
+}
+
+impl LogFormatter for LogfmtFormatter {
+    fn format(&self, message: &LogMessage) -> String {
+        let mut out = String::new();
+        write_pair(&mut out, "time", &message.time.to_rfc3339());
+        write_pair(&mut out, "level", &message.severity.to_string().to_lowercase());
+        write_pair(&mut out, "msg", &message.content);
+        if let Some(target) = &message.target {
+            write_pair(&mut out, "target", target);
+        }
+        if let Some(location) = &message.location {
+            write_pair(&mut out, "location", &location.to_string());
+        }
+        write_pair(&mut out, "thread", &message.thread_id.to_string());
+        if let Some(origin) = &message.origin {
+            write_pair(&mut out, "origin", &origin.component);
+            write_pair(&mut out, "pid", &origin.process_id.to_string());
+        }
+        for (key, value) in &message.fields {
+            write_pair(&mut out, key, value);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::severity::LogSeverity;
+
+    #[test]
+    fn quotes_and_escapes() {
+        let message = LogMessage::new(LogSeverity::Warning, "Matched \"here\"\nand there");
+        let line = LogfmtFormatter::new().format(&message);
+        assert!(line.contains("level=warn "));
+        assert!(line.contains(r#"msg="Matched \"here\"\nand there" "#));
+    }
+}
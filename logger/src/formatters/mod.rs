@@ -0,0 +1,18 @@
+use crate::LogMessage;
+
+pub mod json;
+pub mod logfmt;
+pub mod text;
+
+/// Turns a [`LogMessage`] into the line a logger writes out.
+///
+/// Implementations should not add a trailing newline, the logger takes care of line endings.
+pub trait LogFormatter {
+    fn format(&self, message: &LogMessage) -> String;
+}
+
+impl<F: LogFormatter + ?Sized> LogFormatter for Box<F> {
+    fn format(&self, message: &LogMessage) -> String {
+        (**self).format(message)
+    }
+}
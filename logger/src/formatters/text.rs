@@ -0,0 +1,111 @@
+use crate::LogMessage;
+
+use super::LogFormatter;
+
+/// ANSI colours assigned to origin components, picked by hashing the component name
+const ORIGIN_COLOURS: [&str; 6] = ["\x1b[36m", "\x1b[35m", "\x1b[33m", "\x1b[32m", "\x1b[34m", "\x1b[31m"];
+const RESET: &str = "\x1b[0m";
+
+/// Human readable `(time) [origin] Severity : content` lines.
+///
+/// Line breaks in the content and fields are written as `\n` so every message stays on one line.
+/// The output is meant for people: files meant to be read back by
+/// [`LogReader`](crate::reader::LogReader) or `logq` should use
+/// [`LogfmtFormatter`](super::logfmt::LogfmtFormatter).
+pub struct TextFormatter {
+    time_format: String,
+    colour_origins: bool,
+}
+
+impl Default for TextFormatter {
+    fn default() -> Self {
+        Self {
+            time_format: String::from("%Y-%m-%d %H:%M:%S%.3f"),
+            colour_origins: false,
+        }
+    }
+}
+
+impl TextFormatter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the `chrono` format string used for the timestamp
+    pub fn with_time_format(mut self, format: impl Into<String>) -> Self {
+        self.time_format = format.into();
+        self
+    }
+
+    /// Colours the origin tag with ANSI escape codes, each component keeping the same colour
+    pub fn with_origin_colours(mut self) -> Self {
+        self.colour_origins = true;
+        self
+    }
+
+    fn origin_colour(component: &str) -> &'static str {
+        let hash = component
+            .bytes()
+            .fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize));
+        ORIGIN_COLOURS[hash % ORIGIN_COLOURS.len()]
+    }
+}
+
+impl LogFormatter for TextFormatter {
+    fn format(&self, message: &LogMessage) -> String {
+        let origin = match &message.origin {
+            Some(origin) if self.colour_origins => format!(
+                "{}[{}]{} ",
+                Self::origin_colour(&origin.component),
+                origin.component,
+                RESET
+            ),
+            Some(origin) => format!("[{}] ", origin.component),
+            None => String::new(),
+        };
+        let mut line = format!(
+            "({}) {}{} : {}",
+            message.time.format(&self.time_format),
+            origin,
+            message.severity,
+            escape_line_breaks(&message.content)
+        );
+        for (key, value) in &message.fields {
+            line.push_str(&format!(" {}={}", key, escape_line_breaks(value)));
+        }
+        line
+    }
+}
+
+fn escape_line_breaks(text: &str) -> String {
+    text.replace('\r', "\\r").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogOrigin, severity::LogSeverity};
+
+    #[test]
+    fn formats_time_origin_and_content() {
+        let message = LogMessage::new(LogSeverity::Info, "Found 2 strings\nin the heap")
+            .with_field("count", 2)
+            .with_origin(LogOrigin::new("payload"));
+        let line = TextFormatter::new().with_time_format("%Y").format(&message);
+        let expected = format!(
+            "({}) [payload] {} : Found 2 strings\\nin the heap count=2",
+            message.time.format("%Y"),
+            message.severity
+        );
+        assert_eq!(line, expected);
+        let message = message.with_field("backtrace", "0: main\n1: start");
+        assert!(!TextFormatter::new().format(&message).contains('\n'));
+
+        let coloured = TextFormatter::new().with_origin_colours().format(&message);
+        let colour = TextFormatter::origin_colour("payload");
+        assert!(coloured.contains(&format!("{}[payload]{} ", colour, RESET)));
+        // Every message from a component gets the same colour
+        let other = LogMessage::new(LogSeverity::Debug, "Again").with_origin(LogOrigin::new("payload"));
+        assert!(TextFormatter::new().with_origin_colours().format(&other).contains(colour));
+    }
+}
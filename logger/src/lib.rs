@@ -1,76 +1,378 @@
-use std::{ops::Deref, thread::JoinHandle};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::Deref,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{JoinHandle, ThreadId},
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Local, TimeDelta};
 use serde::{Deserialize, Serialize};
 use severity::LogSeverity;
-use thread_safe_utils::{queue::ThreadSafeQueue, signal::Signal};
+use thread_safe_utils::{
+    queue::{RingQueue, SharedQueue, ThreadSafeQueue, ThreadSafeQueueError},
+    signal::{CancellationToken, Signal, SignallableData},
+};
 
 pub mod severity;
 pub mod loggers;
+pub mod formatters;
+pub mod bridge;
+pub mod config;
+pub mod emergency;
+pub mod error;
+mod macros;
+pub mod net;
+pub mod reader;
+pub mod span;
+pub mod subscription;
+
+pub use error::{LogError, LogResult};
+pub use span::Span;
+pub use subscription::LogSubscription;
+use subscription::Subscribers;
 
 pub trait Logger {
-    fn log(&mut self, message: &LogMessage) -> bool;
+    fn log(&mut self, message: &LogMessage) -> LogResult;
+
+    /// Makes sure everything logged so far has reached its destination
+    fn flush(&mut self) -> LogResult {
+        Ok(())
+    }
+}
+
+impl<L: Logger + ?Sized> Logger for Box<L> {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        (**self).log(message)
+    }
+
+    fn flush(&mut self) -> LogResult {
+        (**self).flush()
+    }
+}
+
+/// Where in the source a message was logged from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Which process and component logged a message, so streams forwarded between processes (such as
+/// the payload's logs sent to the host over IPC) can be told apart
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogOrigin {
+    pub process_id: u32,
+    pub component: String,
+}
+
+impl LogOrigin {
+    /// An origin for `component` in the current process
+    pub fn new(component: impl Into<String>) -> Self {
+        Self {
+            process_id: std::process::id(),
+            component: component.into(),
+        }
+    }
+}
+
+impl Display for LogOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.component, self.process_id)
+    }
 }
 
+// Every field is always serialized (no `skip_serializing_if`/`flatten`) so messages survive the
+// non self-describing bincode trip through IPC.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LogMessage {
     pub time: DateTime<Local>,
     pub severity: LogSeverity,
     pub content: String,
+    /// The module the message originated from, usually filled in by the `log_*!` macros
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub location: Option<SourceLocation>,
+    #[serde(default)]
+    pub thread_id: u64,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Filled in by the [`LogWorker`] that first queues the message, if it has an origin
+    #[serde(default)]
+    pub origin: Option<LogOrigin>,
 }
 
 impl LogMessage {
     pub fn new(severity: LogSeverity, content: impl Into<String>) -> Self {
         let content = content.into();
-        LogMessage { time: Local::now(), severity, content }
+        LogMessage {
+            time: Local::now(),
+            severity,
+            content,
+            target: None,
+            location: None,
+            thread_id: current_thread_id(),
+            fields: BTreeMap::new(),
+            origin: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn with_location(mut self, file: impl Into<String>, line: u32) -> Self {
+        self.location = Some(SourceLocation { file: file.into(), line });
+        self
+    }
+
+    /// Attaches a key/value pair, replacing any existing value for `key`
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Display) -> Self {
+        self.fields.insert(key.into(), value.to_string());
+        self
+    }
+
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(|value| value.as_str())
+    }
+
+    pub fn with_origin(mut self, origin: LogOrigin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// The component of the message's origin, if it has one
+    pub fn component(&self) -> Option<&str> {
+        self.origin.as_ref().map(|origin| origin.component.as_str())
     }
 }
 
+/// A numeric ID for the current thread, matching the number in `ThreadId`'s debug output
+fn current_thread_id() -> u64 {
+    let id = format!("{:?}", std::thread::current().id());
+    id.trim_start_matches("ThreadId(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or_default()
+}
+
+const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// What a [`LogWorker`] does with a new message when the manager's queue is full
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// Wait for the logging thread to make room
+    Block,
+    /// Discard the oldest queued message to make room
+    DropOldest,
+    /// Discard the new message
+    DropNewest,
+}
+
+#[derive(Default)]
+struct DropCounter {
+    /// Drops not yet reported by the logging thread
+    pending: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl DropCounter {
+    fn record(&self) {
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A request for the logging thread to flush its sinks, completed once everything queued before it
+/// has been logged. The data is whether the flush succeeded.
+type FlushRequest = Arc<SignallableData<bool>>;
+
+/// Everything that can be placed in a [`LogManager`]'s queue
+enum QueueItem {
+    Message(LogMessage),
+    Flush(FlushRequest),
+}
+
+fn complete_flush(request: &FlushRequest, flushed: bool) {
+    if let Ok(mut lock) = request.lock() {
+        *lock = flushed;
+    }
+    request.set_signal(true);
+}
+
 /// A struct designed to hold references to queues, threads, and anything else that may be needed
 /// for logging
 pub struct LogManager {
-    queue: ThreadSafeQueue<LogMessage>,
+    queue: SharedQueue<QueueItem>,
     thread: Option<JoinHandle<()>>,
     default_worker: LogWorker,
+    subscribers: Subscribers,
+    /// The origin the logging thread stamps on its own messages, such as drop reports
+    origin: Arc<Mutex<Option<LogOrigin>>>,
 }
 
 impl Drop for LogManager {
     fn drop(&mut self) {
-        self.queue.set_signal(true);
-        if let Some(thread) = self.thread.take() {
-            let _ = thread.join();
-        }
+        self.shutdown();
     }
 }
 
 impl LogManager {
-    pub fn new<F>(mut logger: F) -> Self
+    pub fn new<F>(logger: F) -> Self
+    where
+        F: Logger,
+        F: Send + 'static
+    {
+        Self::with_queue(logger, Arc::new(ThreadSafeQueue::new()), OverflowPolicy::Block)
+    }
+
+    /// Creates a manager whose queue holds at most `capacity` messages, applying `policy` to any
+    /// message logged while it is full.
+    ///
+    /// Dropped messages are reported to the logger as a single warning once the queue drains.
+    pub fn bounded<F>(logger: F, capacity: usize, policy: OverflowPolicy) -> Self
+    where
+        F: Logger,
+        F: Send + 'static
+    {
+        Self::with_queue(logger, Arc::new(ThreadSafeQueue::bounded(capacity)), policy)
+    }
+
+    /// Like [`bounded`](Self::bounded), but queueing through a lock-free [`RingQueue`], so
+    /// threads logging at the same time never wait on each other. `capacity` is rounded up to a
+    /// power of two.
+    pub fn lock_free<F>(logger: F, capacity: usize, policy: OverflowPolicy) -> Self
+    where
+        F: Logger,
+        F: Send + 'static
+    {
+        Self::with_queue(logger, Arc::new(RingQueue::bounded(capacity)), policy)
+    }
+
+    fn with_queue<F>(mut logger: F, queue: SharedQueue<QueueItem>, policy: OverflowPolicy) -> Self
     where
         F: Logger,
         F: Send + 'static
     {
-        let queue = ThreadSafeQueue::new();
-        let thread = Some({
+        let dropped = Arc::new(DropCounter::default());
+        let subscribers = Subscribers::default();
+        let origin = Arc::new(Mutex::new(None));
+        let thread = {
             let queue = queue.clone();
+            let dropped = dropped.clone();
+            let subscribers = subscribers.clone();
+            let origin = origin.clone();
             std::thread::spawn(move || {
-                while let Ok(message) = queue.dequeue() {
-                    if !logger.log(&message) {
-                        queue.set_signal(true);
+                while let Ok(item) = queue.dequeue() {
+                    match item {
+                        // A failing logger is the logger's problem (see `MultiLogger`), it must not
+                        // stop messages reaching anything else
+                        QueueItem::Message(message) => {
+                            let _ = logger.log(&message);
+                            subscribers.publish(&message);
+                        }
+                        QueueItem::Flush(request) => complete_flush(&request, logger.flush().is_ok()),
+                    }
+                    let count = dropped.pending.swap(0, Ordering::Relaxed);
+                    if count > 0 {
+                        let mut report = LogMessage::new(
+                            LogSeverity::Warning,
+                            format!("{} messages dropped", count),
+                        )
+                        .with_target(module_path!())
+                        .with_field("dropped", count);
+                        report.origin = origin.lock().ok().and_then(|origin| origin.clone());
+                        let _ = logger.log(&report);
+                        subscribers.publish(&report);
+                    }
+                }
+                let _ = logger.flush();
+                // Nobody is left to handle these, don't leave flushes waiting forever
+                while let Some(item) = queue.try_dequeue() {
+                    if let QueueItem::Flush(request) = item {
+                        complete_flush(&request, false);
                     }
                 }
             })
-        });
-        let default_worker = LogWorker::new(queue.clone(), Local::now());
+        };
+        let default_worker =
+            LogWorker::new(queue.clone(), thread.thread().id(), Local::now(), policy, dropped);
         Self {
             queue,
-            thread,
+            thread: Some(thread),
             default_worker,
+            subscribers,
+            origin,
         }
     }
 
     pub fn get_log_worker(&self) -> LogWorker {
         self.default_worker.clone()
     }
+
+    /// Tags every message logged through this manager's workers with `component` and the current
+    /// process ID. Messages that already have an origin keep it.
+    pub fn with_origin(mut self, component: impl Into<String>) -> Self {
+        let origin = LogOrigin::new(component);
+        if let Ok(mut shared) = self.origin.lock() {
+            *shared = Some(origin.clone());
+        }
+        self.default_worker.origin = Some(origin);
+        self
+    }
+
+    /// Starts receiving every message at `severity` or above as it is logged, alongside the
+    /// manager's logger
+    pub fn subscribe(&self, severity: LogSeverity) -> LogSubscription {
+        self.subscribe_with_capacity(severity, DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but buffering at most `capacity` messages. Once the
+    /// buffer is full further messages are dropped for this subscriber only.
+    pub fn subscribe_with_capacity(&self, severity: LogSeverity, capacity: usize) -> LogSubscription {
+        self.subscribers.subscribe(severity, capacity)
+    }
+
+    /// The number of messages dropped because the queue was full, over the manager's lifetime
+    pub fn dropped_messages(&self) -> usize {
+        self.default_worker.dropped.total.load(Ordering::Relaxed)
+    }
+
+    /// Stops taking new messages once `token` is cancelled, as [`shutdown`](Self::shutdown) would.
+    /// Messages already queued are still logged.
+    pub fn stop_on(&self, token: &CancellationToken) {
+        let queue = Arc::downgrade(&self.queue);
+        token.on_cancel(move || {
+            if let Some(queue) = queue.upgrade() {
+                queue.close();
+            }
+        });
+    }
+
+    /// Logs everything already queued, flushes the logger and stops the logging thread.
+    ///
+    /// Messages logged through any [`LogWorker`] afterwards are discarded. Called on drop.
+    pub fn shutdown(&mut self) {
+        // The logging thread stops once it has taken everything left in the queue, and producers
+        // blocked on a full queue give up
+        self.queue.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.subscribers.close();
+    }
 }
 
 impl Deref for LogManager {
@@ -83,23 +385,257 @@ impl Deref for LogManager {
 
 #[derive(Clone)]
 pub struct LogWorker {
-    queue: ThreadSafeQueue<LogMessage>,
-    manager_start_time: DateTime<Local>
+    queue: SharedQueue<QueueItem>,
+    logging_thread: ThreadId,
+    manager_start_time: DateTime<Local>,
+    policy: OverflowPolicy,
+    dropped: Arc<DropCounter>,
+    origin: Option<LogOrigin>,
 }
 
 impl LogWorker {
-    fn new(queue: ThreadSafeQueue<LogMessage>, manager_start_time: DateTime<Local>) -> Self {
+    fn new(
+        queue: SharedQueue<QueueItem>,
+        logging_thread: ThreadId,
+        manager_start_time: DateTime<Local>,
+        policy: OverflowPolicy,
+        dropped: Arc<DropCounter>,
+    ) -> Self {
         Self {
             queue,
+            logging_thread,
             manager_start_time,
+            policy,
+            dropped,
+            origin: None,
+        }
+    }
+
+    /// Queues `message` for the logging thread.
+    ///
+    /// Returns false if the message was not queued, either because it was dropped by the
+    /// [`OverflowPolicy`] or because the manager is shutting down.
+    ///
+    /// On the logging thread itself, such as from a logger using the `log` facade through a
+    /// `LogBridge`, this never waits for room: only that thread can make any, so a message that
+    /// doesn't fit is dropped instead.
+    pub fn log(&self, mut message: LogMessage) -> bool {
+        if self.queue.is_closed() {
+            return false;
+        }
+        if message.origin.is_none() {
+            message.origin = self.origin.clone();
+        }
+        if !message.fields.contains_key("span")
+            && let Some(path) = span::current_span_path()
+        {
+            message.fields.insert(String::from("span"), path);
+        }
+        let item = QueueItem::Message(message);
+        match self.policy {
+            OverflowPolicy::Block if std::thread::current().id() == self.logging_thread => {
+                self.enqueue_dropping_newest(item)
+            }
+            OverflowPolicy::Block => self.queue.enqueue(item).is_ok(),
+            OverflowPolicy::DropOldest => self.enqueue_dropping_oldest(item),
+            OverflowPolicy::DropNewest => self.enqueue_dropping_newest(item),
         }
     }
-    pub fn log(&self, message: LogMessage) -> bool {
-        self.queue.enqueue(message).is_ok()
+
+    fn enqueue_dropping_newest(&self, item: QueueItem) -> bool {
+        match self.queue.try_enqueue(item) {
+            Ok(()) => true,
+            Err(e) => {
+                if matches!(e.error(), ThreadSafeQueueError::Full) {
+                    self.dropped.record();
+                }
+                false
+            }
+        }
+    }
+
+    fn enqueue_dropping_oldest(&self, mut item: QueueItem) -> bool {
+        // Control items must never be dropped. Requeueing an evicted one behind this message only
+        // makes it complete later than it otherwise would.
+        for _ in 0..self.queue.capacity().unwrap_or(1) {
+            match self.queue.force_enqueue(item) {
+                Ok(None) => return true,
+                Ok(Some(QueueItem::Message(_))) => {
+                    self.dropped.record();
+                    return true;
+                }
+                Ok(Some(control)) => item = control,
+                Err(_) => return false,
+            }
+        }
+        // Nothing but control items queued, which the logging thread gets through quickly
+        self.queue.enqueue(item).is_ok()
+    }
+
+    /// Blocks until every message queued before this call has been logged and the logger has
+    /// been flushed.
+    ///
+    /// Returns false if `timeout` elapsed first, the flush failed, or the manager has stopped.
+    /// Also returns false straight away when called by a logger on the logging thread, which
+    /// would otherwise wait on itself.
+    pub fn flush(&self, timeout: Option<Duration>) -> bool {
+        if self.queue.is_closed() || std::thread::current().id() == self.logging_thread {
+            return false;
+        }
+        let request: FlushRequest = Arc::new(SignallableData::new(false));
+        let item = QueueItem::Flush(request.clone());
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let queued = match timeout {
+            Some(timeout) => self.queue.enqueue_timeout(item, timeout).is_ok(),
+            None => self.queue.enqueue(item).is_ok(),
+        };
+        if !queued {
+            return false;
+        }
+        let lock = match deadline {
+            Some(deadline) => request.lock_wait_while_timeout(
+                deadline.saturating_duration_since(Instant::now()),
+                |_, done| !done,
+            ),
+            None => request.lock_wait_for_signal().map(Some),
+        };
+        matches!(lock, Ok(Some(flushed)) if *flushed)
     }
 
     pub fn time_since_start(&self) -> TimeDelta {
-        self.manager_start_time.signed_duration_since(Local::now())
+        Local::now().signed_duration_since(self.manager_start_time)
+    }
+
+    /// Opens a [`Span`] that logs its entry and exit under `target` at [`LogSeverity::Debug`].
+    ///
+    /// [`log_span!`] passes the calling module as the target.
+    pub fn span(&self, target: impl Into<String>, name: impl Into<String>) -> Span {
+        self.span_with_severity(target, name, LogSeverity::Debug)
+    }
+
+    pub fn span_with_severity(
+        &self,
+        target: impl Into<String>,
+        name: impl Into<String>,
+        severity: LogSeverity,
+    ) -> Span {
+        Span::enter(self.clone(), target, name, severity)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::*;
+
+    #[test]
+    fn message_survives_bincode() {
+        let message = log_message!(LogSeverity::Debug, { id = 12 }, "Matched at {}", "0x1000")
+            .with_origin(LogOrigin { process_id: 4321, component: String::from("payload") });
+        let config = bincode::config::standard();
+        let bytes = bincode::serde::encode_to_vec(&message, config).unwrap();
+        let (decoded, _): (LogMessage, _) = bincode::serde::decode_from_slice(&bytes, config).unwrap();
+        assert_eq!(decoded.content, message.content);
+        assert_eq!(decoded.target, message.target);
+        assert_eq!(decoded.location, message.location);
+        assert_eq!(decoded.thread_id, message.thread_id);
+        assert_eq!(decoded.fields, message.fields);
+        assert_eq!(decoded.origin, message.origin);
+    }
+
+    struct SlowLogger {
+        delay: Duration,
+        logged: Arc<AtomicUsize>,
+    }
+
+    impl Logger for SlowLogger {
+        fn log(&mut self, _message: &LogMessage) -> LogResult {
+            std::thread::sleep(self.delay);
+            self.logged.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_and_shutdown_drain_queue() {
+        let logged = Arc::new(AtomicUsize::new(0));
+        let logger = SlowLogger { delay: Duration::from_millis(5), logged: logged.clone() };
+        let manager = LogManager::new(logger);
+        for i in 0..10 {
+            assert!(manager.log(LogMessage::new(LogSeverity::Info, format!("{}", i))));
+        }
+        assert!(manager.flush(None));
+        assert_eq!(logged.load(Ordering::Relaxed), 10);
+        for i in 0..10 {
+            assert!(manager.log(LogMessage::new(LogSeverity::Info, format!("{}", i))));
+        }
+        drop(manager);
+        assert_eq!(logged.load(Ordering::Relaxed), 20);
+    }
+
+    /// Flushes the manager it logs for from inside `log`, like a sink using a crate that logs
+    /// through the `log` facade
+    struct ReentrantLogger {
+        worker: Arc<OnceLock<LogWorker>>,
+        flushed: Arc<SignallableData<Option<bool>>>,
+    }
+
+    impl Logger for ReentrantLogger {
+        fn log(&mut self, _message: &LogMessage) -> LogResult {
+            if let Some(worker) = self.worker.get()
+                && let Ok(mut flushed) = self.flushed.lock()
+            {
+                *flushed = Some(worker.flush(None));
+            }
+            self.flushed.set_signal(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_on_the_logging_thread_does_not_wait_on_itself() {
+        let worker = Arc::new(OnceLock::new());
+        let flushed = Arc::new(SignallableData::new(None));
+        let manager = LogManager::new(ReentrantLogger { worker: worker.clone(), flushed: flushed.clone() });
+        assert!(worker.set(manager.get_log_worker()).is_ok());
+        assert!(manager.log(LogMessage::new(LogSeverity::Info, "reentrant")));
+        assert_eq!(*flushed.lock_wait_for_signal().unwrap(), Some(false));
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+    }
+
+    #[test]
+    fn bounded_queue_drops_without_losing_flushes() {
+        let logged = Arc::new(AtomicUsize::new(0));
+        let logger = SlowLogger { delay: Duration::from_millis(2), logged: logged.clone() };
+        let manager = LogManager::bounded(logger, 4, OverflowPolicy::DropOldest);
+        for i in 0..50 {
+            assert!(manager.log(LogMessage::new(LogSeverity::Info, format!("{}", i))));
+        }
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+        let dropped = manager.dropped_messages();
+        assert!(dropped > 0);
+        // Every message was either logged or dropped, plus at least one report of the drops
+        assert!(logged.load(Ordering::Relaxed) + dropped > 50);
+    }
+
+    #[test]
+    fn drop_reports_reach_subscribers_with_the_origin() {
+        let logged = Arc::new(AtomicUsize::new(0));
+        let logger = SlowLogger { delay: Duration::from_millis(2), logged: logged.clone() };
+        let manager = LogManager::bounded(logger, 4, OverflowPolicy::DropNewest).with_origin("host");
+        let warnings = manager.subscribe(LogSeverity::Warning);
+        for i in 0..50 {
+            manager.log(LogMessage::new(LogSeverity::Info, format!("{}", i)));
+        }
+        assert!(manager.flush(Some(Duration::from_secs(5))));
+        let reports = std::iter::from_fn(|| warnings.try_recv()).collect::<Vec<_>>();
+        let reported = reports
+            .iter()
+            .filter_map(|report| report.field("dropped")?.parse::<usize>().ok())
+            .sum::<usize>();
+        assert_eq!(reported, manager.dropped_messages());
+        assert!(reports.iter().all(|report| report.component() == Some("host")));
+    }
+}
@@ -1,13 +1,32 @@
-use crate::Logger;
+use std::io::Write;
 
+use crate::{
+    formatters::{text::TextFormatter, LogFormatter},
+    LogResult, Logger,
+};
 
-#[derive(Default)]
-pub struct ConsoleLogger {}
+
+pub struct ConsoleLogger {
+    formatter: Box<dyn LogFormatter + Send>,
+}
+
+impl Default for ConsoleLogger {
+    fn default() -> Self {
+        Self {
+            formatter: Box::new(TextFormatter::new().with_time_format("%I:%M:%S%p")),
+        }
+    }
+}
 
 impl Logger for ConsoleLogger {
-    fn log(&mut self, message: &crate::LogMessage) -> bool {
-        println!("({}) {} : {}", message.time.format("%I:%M:%S%p"), message.severity, message.content);
-        true
+    fn log(&mut self, message: &crate::LogMessage) -> LogResult {
+        writeln!(std::io::stdout(), "{}", self.formatter.format(message))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> LogResult {
+        std::io::stdout().flush()?;
+        Ok(())
     }
 }
 
@@ -15,4 +34,9 @@ impl ConsoleLogger {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn with_formatter<F: LogFormatter + Send + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Box::new(formatter);
+        self
+    }
 }
@@ -0,0 +1,193 @@
+use std::time::{Duration, Instant};
+
+use crate::{LogMessage, LogResult, Logger};
+
+const DEFAULT_SUMMARY_REPEATS: usize = 1000;
+const DEFAULT_SUMMARY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Collapses identical consecutive messages into a single "repeated N times" message.
+///
+/// Messages are identical if their severity, target, content, origin and fields all match. While a
+/// message keeps repeating, a summary is written every 1000 repeats or 10 seconds, whichever
+/// comes first, so a stuck loop still shows up.
+pub struct DuplicateFilter {
+    next_logger: Box<dyn Logger + Send>,
+    compare_fields: bool,
+    summary_repeats: usize,
+    summary_interval: Duration,
+    last: Option<LogMessage>,
+    repeated: usize,
+    /// When the first repeat not yet summarised was seen
+    repeating_since: Option<Instant>,
+}
+
+impl Logger for DuplicateFilter {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        if self.last.as_ref().is_some_and(|last| self.is_duplicate(last, message)) {
+            self.repeated += 1;
+            let since = *self.repeating_since.get_or_insert_with(Instant::now);
+            if self.repeated >= self.summary_repeats || since.elapsed() >= self.summary_interval {
+                return self.report_repeats();
+            }
+            return Ok(());
+        }
+        let res = self.report_repeats();
+        self.last = Some(message.clone());
+        self.next_logger.log(message).and(res)
+    }
+
+    fn flush(&mut self) -> LogResult {
+        let res = self.report_repeats();
+        self.next_logger.flush().and(res)
+    }
+}
+
+impl DuplicateFilter {
+    pub fn new<L: Logger + Send + 'static>(logger: L) -> Self {
+        Self {
+            next_logger: Box::new(logger),
+            compare_fields: true,
+            summary_repeats: DEFAULT_SUMMARY_REPEATS,
+            summary_interval: DEFAULT_SUMMARY_INTERVAL,
+            last: None,
+            repeated: 0,
+            repeating_since: None,
+        }
+    }
+
+    /// Treat messages as duplicates even if their fields differ, so a line logged in a loop with a
+    /// different field each time is still collapsed and those fields are lost
+    pub fn ignoring_fields(mut self) -> Self {
+        self.compare_fields = false;
+        self
+    }
+
+    /// Write a summary once a message has repeated `repeats` times or has been repeating for
+    /// `interval`, rather than waiting for a different message
+    pub fn with_summary_every(mut self, repeats: usize, interval: Duration) -> Self {
+        self.summary_repeats = repeats.max(1);
+        self.summary_interval = interval;
+        self
+    }
+
+    fn is_duplicate(&self, last: &LogMessage, message: &LogMessage) -> bool {
+        last.severity == message.severity
+            && last.target == message.target
+            && last.content == message.content
+            && last.origin == message.origin
+            && (!self.compare_fields || last.fields == message.fields)
+    }
+
+    fn report_repeats(&mut self) -> LogResult {
+        if self.repeated == 0 {
+            return Ok(());
+        }
+        let count = std::mem::take(&mut self.repeated);
+        self.repeating_since = None;
+        let Some(last) = self.last.as_ref() else {
+            return Ok(());
+        };
+        let mut summary = LogMessage::new(
+            last.severity.clone(),
+            format!("Previous message repeated {} times", count),
+        )
+        .with_field("repeated", count);
+        summary.target = last.target.clone();
+        summary.location = last.location.clone();
+        // Routed like the messages it stands for
+        summary.origin = last.origin.clone();
+        self.next_logger.log(&summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LogOrigin,
+        loggers::ring::{LogQuery, RingBufferLogger},
+        severity::LogSeverity,
+    };
+
+    #[test]
+    fn collapses_consecutive_duplicates() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = DuplicateFilter::new(ring);
+        for _ in 0..5 {
+            filter.log(&LogMessage::new(LogSeverity::Debug, "Matched").with_field("block", 7)).unwrap();
+        }
+        filter.log(&LogMessage::new(LogSeverity::Info, "Done")).unwrap();
+        let contents = handle
+            .query(&LogQuery::new())
+            .into_iter()
+            .map(|m| m.content)
+            .collect::<Vec<_>>();
+        assert_eq!(contents, ["Matched", "Previous message repeated 4 times", "Done"]);
+
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = DuplicateFilter::new(ring).ignoring_fields();
+        for i in 0..5 {
+            filter.log(&LogMessage::new(LogSeverity::Debug, "Matched").with_field("block", i)).unwrap();
+        }
+        filter.flush().unwrap();
+        assert_eq!(handle.query(&LogQuery::new()).len(), 2);
+    }
+
+    #[test]
+    fn messages_differing_only_by_field_are_kept() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = DuplicateFilter::new(ring);
+        filter.log(&LogMessage::new(LogSeverity::Debug, "Send Command").with_field("id", 1)).unwrap();
+        filter.log(&LogMessage::new(LogSeverity::Debug, "Send Command").with_field("id", 2)).unwrap();
+        let ids = handle
+            .query(&LogQuery::new())
+            .into_iter()
+            .map(|m| m.fields["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["1", "2"]);
+    }
+
+    #[test]
+    fn long_runs_are_summarised_without_a_different_message() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = DuplicateFilter::new(ring).with_summary_every(3, Duration::from_secs(60));
+        for _ in 0..7 {
+            filter.log(&LogMessage::new(LogSeverity::Warning, "Retrying")).unwrap();
+        }
+        let contents = handle
+            .query(&LogQuery::new())
+            .into_iter()
+            .map(|m| m.content)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            contents,
+            ["Retrying", "Previous message repeated 3 times", "Previous message repeated 3 times"]
+        );
+    }
+
+    #[test]
+    fn summaries_keep_the_origin_and_location() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = DuplicateFilter::new(ring);
+        let payload = LogOrigin { process_id: 42, component: String::from("payload") };
+        let message = LogMessage::new(LogSeverity::Warning, "Pipe full")
+            .with_location("src/slave/mod.rs", 30)
+            .with_origin(payload.clone());
+        for _ in 0..3 {
+            filter.log(&message).unwrap();
+        }
+        // The same line from another process is not a repeat
+        filter.log(&LogMessage::new(LogSeverity::Warning, "Pipe full").with_origin(LogOrigin::new("host"))).unwrap();
+        let logged = handle.query(&LogQuery::new());
+        assert_eq!(logged.len(), 3);
+        assert_eq!(logged[1].content, "Previous message repeated 2 times");
+        assert_eq!(logged[1].origin, Some(payload));
+        assert_eq!(logged[1].location, message.location);
+        assert_eq!(logged[2].component(), Some("host"));
+    }
+}
@@ -1,84 +1,409 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use crate::Logger;
+use chrono::{DateTime, Local, NaiveDateTime, Timelike};
+
+use crate::{
+    formatters::{text::TextFormatter, LogFormatter},
+    LogError, LogResult, Logger,
+};
 
 pub enum FileConflictBehavior {
+    /// Leave the existing file alone and log to the next free `name_N.ext`
     AppendNumber,
     Append,
     Error,
     Overwrite,
+    /// Move the existing file to the next free `name_old_N.ext` and start fresh
     RenameOld,
 }
 
+/// How rotated files are named, relative to the path the logger was created with
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RotationNaming {
+    /// The active file keeps the configured name, full files are moved to `name_old_N.ext`
+    #[default]
+    RenameOld,
+    /// Full files are left in place and logging continues in `name_N.ext`
+    AppendNumber,
+}
+
+impl RotationNaming {
+    fn marker(&self) -> &'static str {
+        match self {
+            RotationNaming::RenameOld => "_old_",
+            RotationNaming::AppendNumber => "_",
+        }
+    }
+
+    /// The path of the `index`th rotated file for `base`
+    pub fn numbered(&self, base: &Path, index: usize) -> PathBuf {
+        let mut path = base.to_path_buf();
+        path.set_file_name(format!(
+            "{}{}{}",
+            base.file_stem().unwrap_or_default().to_string_lossy(),
+            self.marker(),
+            index
+        ));
+        if let Some(extension) = base.extension() {
+            path.set_extension(extension);
+        }
+        path
+    }
+
+    /// Every rotated file belonging to `base` that currently exists, sorted oldest first
+    pub fn existing(&self, base: &Path) -> Result<Vec<(usize, PathBuf)>, std::io::Error> {
+        let dir = match base.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!(
+            "{}{}",
+            base.file_stem().unwrap_or_default().to_string_lossy(),
+            self.marker()
+        );
+        let suffix = base
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let index = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .and_then(|num| num.parse::<usize>().ok());
+            if let Some(index) = index {
+                files.push((index, entry.path()));
+            }
+        }
+        files.sort_by_key(|(index, _)| *index);
+        Ok(files)
+    }
+
+    /// The first index after every rotated file that already exists
+    pub fn next_index(&self, base: &Path) -> Result<usize, std::io::Error> {
+        Ok(self
+            .existing(base)?
+            .last()
+            .map(|(index, _)| index + 1)
+            .unwrap_or(1))
+    }
+}
+
+/// Calendar boundaries a file can be rotated on, in local time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotationInterval {
+    Hourly,
+    Daily,
+}
+
+impl RotationInterval {
+    /// The start of the period `time` falls in
+    fn period_start(&self, time: &DateTime<Local>) -> NaiveDateTime {
+        let time = time.naive_local();
+        let start = match self {
+            RotationInterval::Hourly => time.date().and_hms_opt(time.hour(), 0, 0),
+            RotationInterval::Daily => time.date().and_hms_opt(0, 0, 0),
+        };
+        start.unwrap_or(time)
+    }
+}
+
+/// Decides when a [`FileLogger`] moves on to a new file and how many old files are kept around.
+///
+/// The default policy never rotates.
+#[derive(Clone, Debug, Default)]
+pub struct RotationPolicy {
+    max_size: Option<u64>,
+    interval: Option<RotationInterval>,
+    keep: Option<usize>,
+    naming: RotationNaming,
+}
+
+impl RotationPolicy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Rotate before a write would push the file past `bytes`
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate on the first message logged after crossing an hour/day boundary
+    pub fn with_interval(mut self, interval: RotationInterval) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Only keep the `count` most recent rotated files, deleting older ones
+    pub fn with_keep(mut self, count: usize) -> Self {
+        self.keep = Some(count);
+        self
+    }
+
+    pub fn with_naming(mut self, naming: RotationNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn naming(&self) -> RotationNaming {
+        self.naming
+    }
+
+    /// Deletes the least recently written files belonging to `base`, other than `active`, until
+    /// at most `keep` remain.
+    ///
+    /// `base` itself counts as one of them once it is no longer written to, as happens with
+    /// [`RotationNaming::AppendNumber`].
+    fn prune(&self, base: &Path, active: &Path) -> Result<(), std::io::Error> {
+        if let Some(keep) = self.keep {
+            let mut files = Vec::new();
+            if base.try_exists()? {
+                files.push(base.to_path_buf());
+            }
+            files.extend(self.naming.existing(base)?.into_iter().map(|(_, path)| path));
+            files.retain(|path| path != active);
+            let mut files = files
+                .into_iter()
+                .map(|path| Ok((std::fs::metadata(&path)?.modified()?, path)))
+                .collect::<Result<Vec<_>, std::io::Error>>()?;
+            // Stable, so files written at the same moment stay in the order they were created
+            files.sort_by_key(|(modified, _)| *modified);
+            for (_, path) in files.iter().take(files.len().saturating_sub(keep)) {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes one line per message as text, unless given another formatter. Files written with
+/// [`LogfmtFormatter`](crate::formatters::logfmt::LogfmtFormatter) can be read back with
+/// [`LogReader`](crate::reader::LogReader).
 pub struct FileLogger {
     file: File,
+    /// The path the logger was created with, rotated names are derived from it
+    base_path: PathBuf,
+    /// The path currently being written to
+    path: PathBuf,
+    policy: RotationPolicy,
+    written: u64,
+    period: Option<NaiveDateTime>,
+    formatter: Box<dyn LogFormatter + Send>,
 }
 
 impl Logger for FileLogger {
-    fn log(&mut self, message: &crate::LogMessage) -> bool {
-        microseh::try_seh(|| {
-            let content = format!("({}) | {:#?} : {}\n", message.time.format("%Y-%b-%d %I:%M%p"), message.severity, message.content);
-            self.file.write_all(content.as_bytes()).is_ok()
-        }).is_ok()
+    fn log(&mut self, message: &crate::LogMessage) -> LogResult {
+        microseh::try_seh(|| -> LogResult {
+            let content = format!("{}\n", self.formatter.format(message));
+            if self.should_rotate(message, content.len() as u64) {
+                self.rotate()?;
+            }
+            self.file.write_all(content.as_bytes())?;
+            self.written += content.len() as u64;
+            Ok(())
+        })
+        .map_err(|e| LogError::Exception(e.to_string()))?
+    }
+
+    fn flush(&mut self) -> LogResult {
+        self.file.flush()?;
+        self.file.sync_data()?;
+        Ok(())
     }
 }
 
 impl FileLogger {
     pub fn new(file: PathBuf, behavior: FileConflictBehavior) -> Result<Self, std::io::Error> {
+        Self::with_rotation(file, behavior, RotationPolicy::default())
+    }
+
+    /// Opens `file` and rotates it according to `policy`.
+    ///
+    /// `AppendNumber` and `RenameOld` use the same naming scheme as rotation, so choosing either
+    /// one also sets the policy's naming.
+    pub fn with_rotation(
+        file: PathBuf,
+        behavior: FileConflictBehavior,
+        mut policy: RotationPolicy,
+    ) -> Result<Self, std::io::Error> {
+        match behavior {
+            FileConflictBehavior::AppendNumber => policy.naming = RotationNaming::AppendNumber,
+            FileConflictBehavior::RenameOld => policy.naming = RotationNaming::RenameOld,
+            _ => {}
+        }
         let exists = file.try_exists()?;
-        let file = if exists {
+        let path = if exists {
             match behavior {
                 FileConflictBehavior::AppendNumber => {
-                    let mut new_file = file.clone();
-                    let mut counter = 1;
-                    while new_file.try_exists()? {
-                        new_file.set_file_name(format!(
-                            "{}_{}",
-                            file.file_stem().unwrap().to_string_lossy(),
-                            counter
-                        ));
-                        if let Some(extension) = file.extension() {
-                            new_file.set_extension(extension);
-                        }
-                        counter += 1;
-                    }
-                    File::create(new_file)?
-                }
-                FileConflictBehavior::Append => {
-                    File::options().append(true).open(file)?
+                    policy.naming.numbered(&file, policy.naming.next_index(&file)?)
                 }
+                FileConflictBehavior::Append => file.clone(),
                 FileConflictBehavior::Error => {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::AlreadyExists,
                         "File already exists",
                     ));
                 }
-                FileConflictBehavior::Overwrite => {
-                    File::create(file)?
-                }
+                FileConflictBehavior::Overwrite => file.clone(),
                 FileConflictBehavior::RenameOld => {
-                    let mut old_file = file.clone();
-                    let mut counter = 1;
-                    while old_file.try_exists()? {
-                        old_file.set_file_name(format!(
-                            "{}_old_{}",
-                            file.file_stem().unwrap().to_string_lossy(),
-                            counter
-                        ));
-                        if let Some(extension) = file.extension() {
-                            old_file.set_extension(extension);
-                        }
-                        counter += 1;
-                    }
+                    let old_file = policy.naming.numbered(&file, policy.naming.next_index(&file)?);
                     std::fs::rename(&file, old_file)?;
-                    File::create(file)?
+                    file.clone()
                 }
             }
         } else {
-            File::create(file)?
+            file.clone()
+        };
+        let handle = if matches!(behavior, FileConflictBehavior::Overwrite) {
+            File::create(&path)?
+        } else {
+            File::options().create(true).append(true).open(&path)?
         };
+        let metadata = handle.metadata()?;
+        let written = metadata.len();
+        // A file appended to may have been started in an earlier period, which has to be rotated
+        // out before anything from this one is written to it
+        let period = match policy.interval {
+            Some(interval) if written > 0 => Some(interval.period_start(&metadata.modified()?.into())),
+            _ => None,
+        };
+        policy.prune(&file, &path)?;
         Ok(Self {
-            file,
+            file: handle,
+            base_path: file,
+            path,
+            policy,
+            written,
+            period,
+            formatter: Box::new(TextFormatter::new().with_time_format("%Y-%b-%d %I:%M%p")),
         })
     }
-}
\ No newline at end of file
+
+    pub fn with_formatter<F: LogFormatter + Send + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Box::new(formatter);
+        self
+    }
+
+    /// The file currently being written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn should_rotate(&mut self, message: &crate::LogMessage, len: u64) -> bool {
+        let mut rotate = false;
+        if let Some(interval) = self.policy.interval {
+            let period = interval.period_start(&message.time);
+            match self.period {
+                Some(current) if period <= current => {}
+                Some(_) => {
+                    self.period = Some(period);
+                    rotate = true;
+                }
+                None => self.period = Some(period),
+            }
+        }
+        if let Some(max_size) = self.policy.max_size {
+            rotate |= self.written > 0 && self.written + len > max_size;
+        }
+        rotate
+    }
+
+    /// Closes the current file and opens a fresh one according to the policy's naming
+    pub fn rotate(&mut self) -> Result<(), std::io::Error> {
+        let _ = self.file.flush();
+        let naming = self.policy.naming;
+        let index = naming.next_index(&self.base_path)?;
+        let path = match naming {
+            RotationNaming::RenameOld => {
+                std::fs::rename(&self.path, naming.numbered(&self.base_path, index))?;
+                self.base_path.clone()
+            }
+            RotationNaming::AppendNumber => naming.numbered(&self.base_path, index),
+        };
+        self.file = File::create(&path)?;
+        self.path = path;
+        self.written = 0;
+        self.policy.prune(&self.base_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogMessage, severity::LogSeverity};
+
+    #[test]
+    fn size_rotation_keeps_last_files() {
+        let dir = std::env::temp_dir().join(format!("logger_rotation_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("test.log");
+        let policy = RotationPolicy::new().with_max_size(64).with_keep(2);
+        let mut logger = FileLogger::with_rotation(base.clone(), FileConflictBehavior::RenameOld, policy).unwrap();
+        for i in 0..10 {
+            assert!(logger.log(&LogMessage::new(LogSeverity::Info, format!("Message number {}", i))).is_ok());
+        }
+        let old = RotationNaming::RenameOld.existing(&base).unwrap();
+        assert_eq!(old.len(), 2);
+        assert!(old.iter().all(|(index, _)| *index > 2));
+        assert!(std::fs::metadata(&base).unwrap().len() <= 64);
+        drop(logger);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keep_counts_every_inactive_file_under_both_namings() {
+        for (naming, behavior) in [
+            (RotationNaming::RenameOld, FileConflictBehavior::RenameOld),
+            (RotationNaming::AppendNumber, FileConflictBehavior::AppendNumber),
+        ] {
+            let dir = std::env::temp_dir().join(format!("logger_keep_{:?}_{}", naming, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let base = dir.join("test.log");
+            let policy = RotationPolicy::new().with_max_size(64).with_keep(2);
+            let mut logger = FileLogger::with_rotation(base.clone(), behavior, policy).unwrap();
+            for i in 0..10 {
+                assert!(logger.log(&LogMessage::new(LogSeverity::Info, format!("Message number {}", i))).is_ok());
+            }
+            let files = std::fs::read_dir(&dir).unwrap().count();
+            assert_eq!(files, 3, "{:?}", naming);
+            assert!(logger.path().exists());
+            if naming == RotationNaming::AppendNumber {
+                // The first file written is the oldest
+                assert!(!base.exists());
+            }
+            drop(logger);
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn appending_to_an_earlier_days_file_rotates_it() {
+        let dir = std::env::temp_dir().join(format!("logger_period_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("test.log");
+        std::fs::write(&base, "yesterday\n").unwrap();
+        let yesterday = std::time::SystemTime::now() - std::time::Duration::from_secs(60 * 60 * 24);
+        File::options().write(true).open(&base).unwrap().set_modified(yesterday).unwrap();
+
+        let policy = RotationPolicy::new().with_interval(RotationInterval::Daily);
+        let mut logger = FileLogger::with_rotation(base.clone(), FileConflictBehavior::Append, policy).unwrap();
+        assert!(logger.log(&LogMessage::new(LogSeverity::Info, "today")).is_ok());
+        let old = RotationNaming::RenameOld.existing(&base).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(std::fs::read_to_string(&old[0].1).unwrap(), "yesterday\n");
+        assert!(!std::fs::read_to_string(&base).unwrap().contains("yesterday"));
+        drop(logger);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,26 +1,145 @@
-use crate::{severity::LogSeverity, Logger};
+use std::str::FromStr;
 
+use thiserror::Error;
+
+use crate::{severity::{LogSeverity, ParseSeverityError}, LogResult, Logger};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FilterParseError {
+    #[error("Invalid directive `{0}`")]
+    InvalidDirective(String),
+    #[error(transparent)]
+    Severity(#[from] ParseSeverityError),
+}
+
+/// Per-target severity thresholds, e.g. `windows_fns=verbose,client::master=info,*=warn`.
+///
+/// A directive applies to its target and every module below it, the most specific directive wins.
+/// `*=level` or a bare `level` sets the threshold for everything else, including messages without
+/// a target.
+#[derive(Clone, Debug)]
+pub struct FilterDirectives {
+    directives: Vec<(String, LogSeverity)>,
+    default: LogSeverity,
+}
+
+impl FilterDirectives {
+    pub fn new(default: LogSeverity) -> Self {
+        Self {
+            directives: Vec::new(),
+            default,
+        }
+    }
+
+    pub fn with_directive(mut self, target: impl Into<String>, level: LogSeverity) -> Self {
+        let target = target.into();
+        self.directives.retain(|(existing, _)| *existing != target);
+        self.directives.push((target, level));
+        // Longest first so the first match is the most specific one
+        self.directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        self
+    }
+
+    /// Parses directives from the environment variable `var`, falling back to `default` if it isn't set
+    pub fn from_env(var: &str, default: LogSeverity) -> Result<Self, FilterParseError> {
+        match std::env::var(var) {
+            Ok(value) => Self::new(default).parse_into(&value),
+            Err(_) => Ok(Self::new(default)),
+        }
+    }
+
+    fn parse_into(mut self, s: &str) -> Result<Self, FilterParseError> {
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    let level = level.parse()?;
+                    if target.is_empty() {
+                        return Err(FilterParseError::InvalidDirective(directive.to_string()));
+                    } else if target == "*" {
+                        self.default = level;
+                    } else {
+                        self = self.with_directive(target, level);
+                    }
+                }
+                None => self.default = directive.parse()?,
+            }
+        }
+        Ok(self)
+    }
+
+    /// The threshold that applies to messages from `target`
+    pub fn level_for(&self, target: Option<&str>) -> &LogSeverity {
+        target
+            .and_then(|target| {
+                self.directives.iter().find(|(directive, _)| {
+                    target
+                        .strip_prefix(directive.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+                })
+            })
+            .map(|(_, level)| level)
+            .unwrap_or(&self.default)
+    }
+
+    pub fn enabled(&self, message: &crate::LogMessage) -> bool {
+        message.severity <= *self.level_for(message.target.as_deref())
+    }
+}
+
+impl FromStr for FilterDirectives {
+    type Err = FilterParseError;
+
+    /// Parses directives on top of a default of [`LogSeverity::Info`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(LogSeverity::Info).parse_into(s)
+    }
+}
 
 pub struct LogFilter {
     next_logger: Box<dyn Logger + Send>,
-    log_level: LogSeverity,
+    directives: FilterDirectives,
 }
 
 impl Logger for LogFilter {
-    fn log(&mut self, message: &crate::LogMessage) -> bool {
-        if message.severity <= self.log_level {
+    fn log(&mut self, message: &crate::LogMessage) -> LogResult {
+        if self.directives.enabled(message) {
             self.next_logger.log(message)
         } else {
-            true
+            Ok(())
         }
     }
+
+    fn flush(&mut self) -> LogResult {
+        self.next_logger.flush()
+    }
 }
 
 impl LogFilter {
     pub fn new<L: Logger + Send + 'static>(sev: LogSeverity, logger: L) -> Self {
+        Self::with_directives(FilterDirectives::new(sev), logger)
+    }
+
+    pub fn with_directives<L: Logger + Send + 'static>(directives: FilterDirectives, logger: L) -> Self {
         Self {
             next_logger: Box::new(logger),
-            log_level: sev
+            directives,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_specific_directive_wins() {
+        let directives: FilterDirectives = "windows_fns=verbose,client::master=info,client=error,*=warn".parse().unwrap();
+        assert_eq!(*directives.level_for(Some("windows_fns::memory::walk")), LogSeverity::Verbose);
+        assert_eq!(*directives.level_for(Some("client::master")), LogSeverity::Info);
+        assert_eq!(*directives.level_for(Some("client::slave")), LogSeverity::Error);
+        assert_eq!(*directives.level_for(Some("client_info")), LogSeverity::Warning);
+        assert_eq!(*directives.level_for(None), LogSeverity::Warning);
+        assert!("client=loud".parse::<FilterDirectives>().is_err());
+    }
+}
@@ -2,4 +2,8 @@ pub mod multi;
 pub mod console;
 pub mod file;
 pub mod filter;
-pub mod null;
\ No newline at end of file
+pub mod null;
+pub mod ring;
+pub mod dedup;
+pub mod rate_limit;
+pub mod origin;
\ No newline at end of file
@@ -1,17 +1,126 @@
-use crate::Logger;
+use std::time::{Duration, Instant};
 
+use crate::{severity::LogSeverity, LogError, LogMessage, LogResult, Logger};
+
+/// How a [`MultiLogger`] treats a logger that keeps failing
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Immediate retries of a failed message before counting it as a failure
+    pub retries: u32,
+    /// How long a logger is skipped after its first failure, doubled for every failure after that
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failures after which a logger is disabled, `None` to never disable
+    pub max_failures: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 1,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_failures: Some(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// The health of one logger owned by a [`MultiLogger`]
+#[derive(Clone, Debug, Default)]
+pub struct SinkHealth {
+    pub name: String,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    /// Messages not sent to this logger because it was backing off or disabled
+    pub skipped: u64,
+    /// Messages the logger dropped with [`LogError::Dropped`], which don't count as failures
+    pub dropped: u64,
+    pub retry_at: Option<Instant>,
+    pub disabled: bool,
+}
+
+impl SinkHealth {
+    fn available(&self, now: Instant) -> bool {
+        !self.disabled && self.retry_at.is_none_or(|at| now >= at)
+    }
+}
+
+struct Sink {
+    logger: Box<dyn Logger + Send>,
+    health: SinkHealth,
+}
+
+/// Sends every message to each of its loggers.
+///
+/// A failing logger doesn't stop the others: it is retried according to the [`RetryPolicy`],
+/// skipped while backing off, and eventually disabled. The remaining loggers are told when that
+/// happens.
+///
+/// Logging only fails for loggers that were tried and failed. Skipped loggers and messages a
+/// logger dropped with [`LogError::Dropped`] show up in [`health`](Self::health), unless every
+/// logger was skipped, which fails with [`LogError::Disabled`].
 #[derive(Default)]
 pub struct MultiLogger {
-    loggers: Vec<Box<dyn Logger + Send>>,
+    sinks: Vec<Sink>,
+    policy: RetryPolicy,
 }
 
 impl Logger for MultiLogger {
-    fn log(&mut self, message: &crate::LogMessage) -> bool {
-        let mut res = true;
-        for logger in &mut self.loggers {
-            res = res && logger.log(message);
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let now = Instant::now();
+        let (mut attempted, mut failed) = (0, 0);
+        let mut disabled = Vec::new();
+        for (index, sink) in self.sinks.iter_mut().enumerate() {
+            if !sink.health.available(now) {
+                sink.health.skipped += 1;
+                continue;
+            }
+            attempted += 1;
+            let mut res = sink.logger.log(message);
+            for _ in 0..self.policy.retries {
+                if matches!(res, Ok(()) | Err(LogError::Dropped)) {
+                    break;
+                }
+                res = sink.logger.log(message);
+            }
+            match res {
+                Ok(()) => {
+                    sink.health.consecutive_failures = 0;
+                    sink.health.retry_at = None;
+                }
+                // Only this message is lost, the logger itself is fine
+                Err(LogError::Dropped) => sink.health.dropped += 1,
+                Err(e) => {
+                    failed += 1;
+                    if Self::record_failure(&mut sink.health, &self.policy, now) {
+                        disabled.push((index, e));
+                    }
+                }
+            }
+        }
+        for (index, error) in disabled {
+            self.report_disabled(index, &error);
+        }
+        Self::result(attempted, failed, self.sinks.len())
+    }
+
+    fn flush(&mut self) -> LogResult {
+        let now = Instant::now();
+        let (mut attempted, mut failed) = (0, 0);
+        for sink in self.sinks.iter_mut().filter(|s| s.health.available(now)) {
+            attempted += 1;
+            if sink.logger.flush().is_err() {
+                failed += 1;
+            }
         }
-        res
+        Self::result(attempted, failed, self.sinks.len())
     }
 }
 
@@ -19,8 +128,156 @@ impl MultiLogger {
     pub fn new() -> Self {
         Default::default()
     }
-    pub fn with_logger<T: Logger + Send + 'static>(mut self, logger: T) -> Self {
-        self.loggers.push(Box::new(logger));
+    pub fn with_logger<T: Logger + Send + 'static>(self, logger: T) -> Self {
+        let name = format!("logger {}", self.sinks.len());
+        self.with_named_logger(name, logger)
+    }
+
+    /// Adds a logger with a name used when reporting its health
+    pub fn with_named_logger<T: Logger + Send + 'static>(mut self, name: impl Into<String>, logger: T) -> Self {
+        self.sinks.push(Sink {
+            logger: Box::new(logger),
+            health: SinkHealth {
+                name: name.into(),
+                ..Default::default()
+            },
+        });
         self
     }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn health(&self) -> Vec<SinkHealth> {
+        self.sinks.iter().map(|s| s.health.clone()).collect()
+    }
+
+    /// Re-enables every disabled logger and clears their backoff
+    pub fn reset(&mut self) {
+        for sink in &mut self.sinks {
+            sink.health.disabled = false;
+            sink.health.consecutive_failures = 0;
+            sink.health.retry_at = None;
+        }
+    }
+
+    fn result(attempted: usize, failed: usize, sinks: usize) -> LogResult {
+        match (attempted, failed) {
+            (0, _) if sinks > 0 => Err(LogError::Disabled),
+            (_, 0) => Ok(()),
+            (total, failed) => Err(LogError::PartialFailure { failed, total }),
+        }
+    }
+
+    /// Returns true if this failure disabled the logger
+    fn record_failure(health: &mut SinkHealth, policy: &RetryPolicy, now: Instant) -> bool {
+        health.consecutive_failures += 1;
+        health.total_failures += 1;
+        if policy
+            .max_failures
+            .is_some_and(|max| health.consecutive_failures >= max)
+        {
+            health.disabled = true;
+            health.retry_at = None;
+            true
+        } else {
+            health.retry_at = Some(now + policy.backoff(health.consecutive_failures));
+            false
+        }
+    }
+
+    fn report_disabled(&mut self, index: usize, error: &LogError) {
+        let health = &self.sinks[index].health;
+        let message = LogMessage::new(
+            LogSeverity::Error,
+            format!(
+                "Disabled {} after {} consecutive failures. {}",
+                health.name, health.consecutive_failures, error
+            ),
+        )
+        .with_target(module_path!())
+        .with_field("logger", &health.name);
+        let now = Instant::now();
+        for sink in self.sinks.iter_mut().filter(|s| s.health.available(now)) {
+            let _ = sink.logger.log(&message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use thread_safe_utils::queue::{ThreadSafeQueue, ThreadSafeQueueError};
+
+    use super::*;
+    use crate::loggers::ring::{LogQuery, RingBufferLogger};
+
+    struct BrokenLogger;
+
+    impl Logger for BrokenLogger {
+        fn log(&mut self, _message: &LogMessage) -> LogResult {
+            Err(LogError::Closed)
+        }
+    }
+
+    #[test]
+    fn failing_logger_is_isolated_and_disabled() {
+        let ring = RingBufferLogger::new(20);
+        let handle = ring.handle();
+        let policy = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            max_failures: Some(3),
+            ..Default::default()
+        };
+        let mut multi = MultiLogger::new()
+            .with_named_logger("broken", BrokenLogger)
+            .with_named_logger("ring", ring)
+            .with_retry_policy(policy);
+        for i in 0..3 {
+            assert!(multi.log(&LogMessage::new(LogSeverity::Info, format!("{}", i))).is_err());
+        }
+        // Delivered to every logger still running
+        for i in 3..5 {
+            assert!(multi.log(&LogMessage::new(LogSeverity::Info, format!("{}", i))).is_ok());
+        }
+        assert_eq!(handle.query(&LogQuery::new().with_severity(LogSeverity::Info)).len(), 6);
+        assert_eq!(handle.query(&LogQuery::new().with_text("disabled broken")).len(), 1);
+        let health = multi.health();
+        assert!(health[0].disabled);
+        assert_eq!(health[0].skipped, 2);
+        assert!(!health[1].disabled);
+    }
+
+    /// Hands messages to a bounded queue without waiting, like the payload's IPC logger
+    struct QueueLogger {
+        queue: ThreadSafeQueue<String>,
+    }
+
+    impl Logger for QueueLogger {
+        fn log(&mut self, message: &LogMessage) -> LogResult {
+            self.queue.try_enqueue(message.content.clone()).map_err(|e| match e.error() {
+                ThreadSafeQueueError::Full => LogError::Dropped,
+                _ => LogError::Queue(e.into()),
+            })
+        }
+    }
+
+    #[test]
+    fn full_queue_drops_without_disabling_the_logger() {
+        let queue = ThreadSafeQueue::bounded(2);
+        let mut multi = MultiLogger::new().with_named_logger("ipc", QueueLogger { queue: queue.clone() });
+        for i in 0..20 {
+            assert!(multi.log(&LogMessage::new(LogSeverity::Info, format!("{}", i))).is_ok());
+        }
+        let health = multi.health();
+        assert!(!health[0].disabled);
+        assert_eq!(health[0].dropped, 18);
+        assert_eq!(health[0].total_failures, 0);
+
+        // Forwarding resumes once the queue drains
+        while queue.try_dequeue().is_some() {}
+        assert!(multi.log(&LogMessage::new(LogSeverity::Info, "resumed")).is_ok());
+        assert_eq!(queue.try_dequeue().as_deref(), Some("resumed"));
+    }
 }
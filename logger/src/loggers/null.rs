@@ -1,4 +1,4 @@
-use crate::Logger;
+use crate::{LogResult, Logger};
 
 
 #[derive(Default)]
@@ -11,7 +11,7 @@ impl NullLogger {
 }
 
 impl Logger for NullLogger {
-    fn log(&mut self, _message: &crate::LogMessage) -> bool {
-        true
+    fn log(&mut self, _message: &crate::LogMessage) -> LogResult {
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use crate::{LogMessage, LogResult, Logger};
+
+/// Only passes on messages from (or, if excluding, not from) a set of origin components, for
+/// example to send the payload's forwarded logs to their own file.
+///
+/// Messages without an origin are treated as having the component `""`.
+pub struct OriginFilter {
+    next_logger: Box<dyn Logger + Send>,
+    components: Vec<String>,
+    exclude: bool,
+}
+
+impl Logger for OriginFilter {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let component = message.component().unwrap_or_default();
+        let listed = self.components.iter().any(|c| c == component);
+        if listed != self.exclude {
+            self.next_logger.log(message)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> LogResult {
+        self.next_logger.flush()
+    }
+}
+
+impl OriginFilter {
+    /// Passes on messages from `component`
+    pub fn new<L: Logger + Send + 'static>(component: impl Into<String>, logger: L) -> Self {
+        Self {
+            next_logger: Box::new(logger),
+            components: vec![component.into()],
+            exclude: false,
+        }
+    }
+
+    /// Passes on messages from anything but `component`
+    pub fn excluding<L: Logger + Send + 'static>(component: impl Into<String>, logger: L) -> Self {
+        Self {
+            exclude: true,
+            ..Self::new(component, logger)
+        }
+    }
+
+    /// Adds another component to match against
+    pub fn with_component(mut self, component: impl Into<String>) -> Self {
+        self.components.push(component.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LogOrigin,
+        loggers::ring::{LogQuery, RingBufferHandle, RingBufferLogger},
+        severity::LogSeverity,
+    };
+
+    fn log_from_each(filter: &mut OriginFilter) {
+        for component in ["payload", "host", "injector"] {
+            let message = LogMessage::new(LogSeverity::Info, component).with_origin(LogOrigin::new(component));
+            filter.log(&message).unwrap();
+        }
+        filter.log(&LogMessage::new(LogSeverity::Info, "unknown")).unwrap();
+    }
+
+    fn contents(ring: &RingBufferHandle) -> Vec<String> {
+        ring.query(&LogQuery::new()).into_iter().map(|m| m.content).collect()
+    }
+
+    #[test]
+    fn routes_by_component() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = OriginFilter::new("payload", ring);
+        log_from_each(&mut filter);
+        assert_eq!(contents(&handle), ["payload"]);
+
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = OriginFilter::new("payload", ring).with_component("injector");
+        log_from_each(&mut filter);
+        assert_eq!(contents(&handle), ["payload", "injector"]);
+
+        // Messages without an origin are excluded only if `""` is listed
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let mut filter = OriginFilter::excluding("payload", ring).with_component("host");
+        log_from_each(&mut filter);
+        assert_eq!(contents(&handle), ["injector", "unknown"]);
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Instant;
+
+use crate::{severity::LogSeverity, LogMessage, LogResult, Logger};
+
+/// A token bucket holding up to `burst` tokens, refilled at `rate` tokens per second
+struct Bucket {
+    severity: LogSeverity,
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+    suppressed: usize,
+}
+
+impl Bucket {
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Limits how many messages of a severity reach the next logger, using a token bucket per severity.
+///
+/// Suppressed messages are counted and reported once messages of that severity are let through
+/// again, or when the logger is flushed.
+pub struct RateLimiter {
+    next_logger: Box<dyn Logger + Send>,
+    buckets: Vec<Bucket>,
+}
+
+impl Logger for RateLimiter {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let now = Instant::now();
+        let Some(bucket) = self.buckets.iter_mut().find(|b| b.severity == message.severity) else {
+            return self.next_logger.log(message);
+        };
+        if !bucket.try_take(now) {
+            bucket.suppressed += 1;
+            return Ok(());
+        }
+        let suppressed = std::mem::take(&mut bucket.suppressed);
+        let res = Self::report(&mut self.next_logger, &message.severity, suppressed);
+        self.next_logger.log(message).and(res)
+    }
+
+    fn flush(&mut self) -> LogResult {
+        let mut res = Ok(());
+        for bucket in &mut self.buckets {
+            let suppressed = std::mem::take(&mut bucket.suppressed);
+            res = Self::report(&mut self.next_logger, &bucket.severity, suppressed).and(res);
+        }
+        self.next_logger.flush().and(res)
+    }
+}
+
+impl RateLimiter {
+    pub fn new<L: Logger + Send + 'static>(logger: L) -> Self {
+        Self {
+            next_logger: Box::new(logger),
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Lets through at most `burst` messages of `severity` at once, refilling at `per_second`
+    pub fn with_limit(mut self, severity: LogSeverity, per_second: f64, burst: u32) -> Self {
+        self.buckets.retain(|b| b.severity != severity);
+        self.buckets.push(Bucket {
+            severity,
+            rate: per_second.max(0.0),
+            burst: burst as f64,
+            tokens: burst as f64,
+            last_refill: Instant::now(),
+            suppressed: 0,
+        });
+        self
+    }
+
+    fn report(logger: &mut Box<dyn Logger + Send>, severity: &LogSeverity, suppressed: usize) -> LogResult {
+        if suppressed == 0 {
+            return Ok(());
+        }
+        let summary = LogMessage::new(
+            severity.clone(),
+            format!("{} {} messages suppressed by rate limit", suppressed, severity),
+        )
+        .with_target(module_path!())
+        .with_field("suppressed", suppressed);
+        logger.log(&summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loggers::ring::{LogQuery, RingBufferLogger};
+
+    #[test]
+    fn limits_per_severity() {
+        let ring = RingBufferLogger::new(20);
+        let handle = ring.handle();
+        let mut limiter = RateLimiter::new(ring).with_limit(LogSeverity::Debug, 0.0, 3);
+        for _ in 0..10 {
+            limiter.log(&LogMessage::new(LogSeverity::Debug, "Matched")).unwrap();
+            limiter.log(&LogMessage::new(LogSeverity::Info, "Not limited")).unwrap();
+        }
+        assert_eq!(handle.query(&LogQuery::new().with_text("matched")).len(), 3);
+        assert_eq!(handle.query(&LogQuery::new().with_text("not limited")).len(), 10);
+        limiter.flush().unwrap();
+        let summary = handle.recent(1).pop().unwrap();
+        assert_eq!(summary.field("suppressed"), Some("7"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{severity::LogSeverity, LogError, LogMessage, LogResult, Logger};
+
+/// Criteria for [`RingBufferHandle::query`], every criterion left unset matches everything
+#[derive(Clone, Debug, Default)]
+pub struct LogQuery {
+    severity: Option<LogSeverity>,
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    text: Option<String>,
+    origins: Vec<String>,
+    #[cfg(feature = "query")]
+    pattern: Option<regex::Regex>,
+    limit: Option<usize>,
+}
+
+impl LogQuery {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only match messages at least as severe as `severity`
+    pub fn with_severity(mut self, severity: LogSeverity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn with_since(mut self, time: DateTime<Local>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    pub fn with_until(mut self, time: DateTime<Local>) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Only match messages whose content contains `text`, ignoring case
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into().to_lowercase());
+        self
+    }
+
+    /// Only match messages from `component`, can be given several times to match any of them
+    pub fn with_origin(mut self, component: impl Into<String>) -> Self {
+        self.origins.push(component.into());
+        self
+    }
+
+    /// Only match messages whose content matches `pattern`
+    #[cfg(feature = "query")]
+    pub fn with_pattern(mut self, pattern: regex::Regex) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Only return the `count` most recent matches
+    pub fn with_limit(mut self, count: usize) -> Self {
+        self.limit = Some(count);
+        self
+    }
+
+    pub fn matches(&self, message: &LogMessage) -> bool {
+        self.severity.as_ref().is_none_or(|sev| message.severity <= *sev)
+            && self.since.is_none_or(|since| message.time >= since)
+            && self.until.is_none_or(|until| message.time <= until)
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| message.content.to_lowercase().contains(text))
+            && (self.origins.is_empty()
+                || message
+                    .component()
+                    .is_some_and(|component| self.origins.iter().any(|c| c == component)))
+            && self.matches_pattern(message)
+    }
+
+    #[cfg(feature = "query")]
+    fn matches_pattern(&self, message: &LogMessage) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&message.content))
+    }
+
+    #[cfg(not(feature = "query"))]
+    fn matches_pattern(&self, _message: &LogMessage) -> bool {
+        true
+    }
+}
+
+/// A cheap, cloneable view into a [`RingBufferLogger`]'s messages that can be kept after the
+/// logger itself has been handed to a [`LogManager`](crate::LogManager)
+#[derive(Clone)]
+pub struct RingBufferHandle {
+    messages: Arc<Mutex<VecDeque<LogMessage>>>,
+}
+
+impl RingBufferHandle {
+    /// Every buffered message matching `query`, oldest first
+    pub fn query(&self, query: &LogQuery) -> Vec<LogMessage> {
+        let Ok(messages) = self.messages.lock() else {
+            return Vec::new();
+        };
+        let mut matches = messages
+            .iter()
+            .rev()
+            .filter(|message| query.matches(message))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect::<Vec<_>>();
+        matches.reverse();
+        matches
+    }
+
+    /// The `count` most recent messages, oldest first
+    pub fn recent(&self, count: usize) -> Vec<LogMessage> {
+        self.query(&LogQuery::new().with_limit(count))
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.lock().map(|l| l.len()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut messages) = self.messages.lock() {
+            messages.clear();
+        }
+    }
+}
+
+/// Keeps the last `capacity` messages in memory
+pub struct RingBufferLogger {
+    capacity: usize,
+    handle: RingBufferHandle,
+}
+
+impl Logger for RingBufferLogger {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let mut messages = self.handle.messages.lock().map_err(|_| LogError::MutexPoisoned)?;
+        while messages.len() >= self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back(message.clone());
+        Ok(())
+    }
+}
+
+impl RingBufferLogger {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            handle: RingBufferHandle {
+                messages: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            },
+        }
+    }
+
+    pub fn handle(&self) -> RingBufferHandle {
+        self.handle.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_last_messages() {
+        let mut logger = RingBufferLogger::new(3);
+        let handle = logger.handle();
+        for (i, sev) in [LogSeverity::Info, LogSeverity::Error, LogSeverity::Debug, LogSeverity::Warning]
+            .into_iter()
+            .enumerate()
+        {
+            logger.log(&LogMessage::new(sev, format!("Message {}", i))).unwrap();
+        }
+        assert_eq!(handle.len(), 3);
+        let contents = |messages: Vec<LogMessage>| messages.into_iter().map(|m| m.content).collect::<Vec<_>>();
+        assert_eq!(contents(handle.recent(2)), ["Message 2", "Message 3"]);
+        assert_eq!(contents(handle.query(&LogQuery::new().with_severity(LogSeverity::Warning))), ["Message 1", "Message 3"]);
+        assert_eq!(contents(handle.query(&LogQuery::new().with_text("MESSAGE 2"))), ["Message 2"]);
+    }
+}
@@ -0,0 +1,86 @@
+/// Builds a [`LogMessage`](crate::LogMessage) tagged with the calling module, file and line.
+///
+/// Fields can be attached by passing them in braces before the format arguments:
+/// `log_message!(LogSeverity::Info, { id = 3, command = cmd }, "Sent command")`
+#[macro_export]
+macro_rules! log_message {
+    ($severity:expr, { $($key:ident = $value:expr),* $(,)? }, $($arg:tt)+) => {
+        $crate::LogMessage::new($severity, format!($($arg)+))
+            .with_target(module_path!())
+            .with_location(file!(), line!())
+            $(.with_field(stringify!($key), &$value))*
+    };
+    ($severity:expr, $($arg:tt)+) => {
+        $crate::log_message!($severity, {}, $($arg)+)
+    };
+}
+
+/// Logs an error through anything with a `log(LogMessage)` method, such as a
+/// [`LogWorker`](crate::LogWorker)
+#[macro_export]
+macro_rules! log_error {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log($crate::log_message!($crate::severity::LogSeverity::Error, $($arg)+))
+    };
+}
+
+/// Logs a warning, see [`log_error!`]
+#[macro_export]
+macro_rules! log_warn {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log($crate::log_message!($crate::severity::LogSeverity::Warning, $($arg)+))
+    };
+}
+
+/// Logs an info message, see [`log_error!`]
+#[macro_export]
+macro_rules! log_info {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log($crate::log_message!($crate::severity::LogSeverity::Info, $($arg)+))
+    };
+}
+
+/// Logs a debug message, see [`log_error!`]
+#[macro_export]
+macro_rules! log_debug {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log($crate::log_message!($crate::severity::LogSeverity::Debug, $($arg)+))
+    };
+}
+
+/// Logs a verbose message, see [`log_error!`]
+#[macro_export]
+macro_rules! log_verbose {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log($crate::log_message!($crate::severity::LogSeverity::Verbose, $($arg)+))
+    };
+}
+
+/// Opens a [`Span`](crate::Span) logged under the calling module, at
+/// [`LogSeverity::Debug`](crate::severity::LogSeverity::Debug) unless a severity is given:
+/// `log_span!(worker, "memory walk")` or `log_span!(worker, LogSeverity::Info, "memory walk")`
+#[macro_export]
+macro_rules! log_span {
+    ($worker:expr, $name:expr) => {
+        $worker.span(module_path!(), $name)
+    };
+    ($worker:expr, $severity:expr, $name:expr) => {
+        $worker.span_with_severity(module_path!(), $name, $severity)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::severity::LogSeverity;
+
+    #[test]
+    fn captures_metadata() {
+        let id = 7;
+        let message = log_message!(LogSeverity::Info, { id = id, name = "walk" }, "Sent {}", "command");
+        assert_eq!(message.content, "Sent command");
+        assert_eq!(message.target.as_deref(), Some(module_path!()));
+        assert_eq!(message.location.as_ref().map(|loc| loc.file.as_str()), Some(file!()));
+        assert_eq!(message.field("id"), Some("7"));
+        assert_eq!(message.field("name"), Some("walk"));
+    }
+}
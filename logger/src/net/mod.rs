@@ -0,0 +1,108 @@
+//! Streaming [`LogMessage`]s between processes or machines.
+//!
+//! Every frame is a 4 byte big-endian length followed by the encoded message. Over UDP each datagram
+//! carries exactly one frame.
+
+use std::{
+    io::{ErrorKind, Read},
+    net::SocketAddr,
+};
+
+use crate::{LogError, LogMessage};
+
+mod receiver;
+mod sink;
+
+pub use receiver::LogReceiver;
+pub use sink::NetworkLogger;
+
+/// Frames larger than this are rejected instead of allocating whatever length a peer sends
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Where a [`NetworkLogger`] sends to and a [`LogReceiver`] listens on
+#[derive(Clone, Debug)]
+pub enum NetworkTarget {
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrameFormat {
+    #[default]
+    Json,
+    Bincode,
+}
+
+impl FrameFormat {
+    /// Encodes `message` as a complete frame, length prefix included
+    pub fn encode(&self, message: &LogMessage) -> Result<Vec<u8>, LogError> {
+        let payload = match self {
+            FrameFormat::Json => {
+                serde_json::to_vec(message).map_err(|e| LogError::Codec(e.to_string()))?
+            }
+            FrameFormat::Bincode => {
+                bincode::serde::encode_to_vec(message, bincode::config::standard())
+                    .map_err(|e| LogError::Codec(e.to_string()))?
+            }
+        };
+        if payload.len() > MAX_FRAME_LEN {
+            return Err(LogError::Codec(format!(
+                "Frame of {} bytes is too large",
+                payload.len()
+            )));
+        }
+        let mut frame = Vec::with_capacity(payload.len() + 4);
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Decodes a frame's payload, without the length prefix
+    pub fn decode(&self, payload: &[u8]) -> Result<LogMessage, LogError> {
+        match self {
+            FrameFormat::Json => {
+                serde_json::from_slice(payload).map_err(|e| LogError::Codec(e.to_string()))
+            }
+            FrameFormat::Bincode => {
+                bincode::serde::decode_from_slice(payload, bincode::config::standard())
+                    .map(|(message, _)| message)
+                    .map_err(|e| LogError::Codec(e.to_string()))
+            }
+        }
+    }
+
+    /// Decodes a whole frame, checking the length prefix against its size
+    pub fn decode_frame(&self, frame: &[u8]) -> Result<LogMessage, LogError> {
+        let (len, payload) = frame
+            .split_first_chunk::<4>()
+            .ok_or_else(|| LogError::Codec(String::from("Frame is missing its length")))?;
+        if u32::from_be_bytes(*len) as usize != payload.len() {
+            return Err(LogError::Codec(String::from(
+                "Frame length does not match its size",
+            )));
+        }
+        self.decode(payload)
+    }
+}
+
+/// Reads one frame's payload from a stream. Returns `None` if the stream ended cleanly between frames.
+pub(crate) fn read_frame(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Frame is too large",
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
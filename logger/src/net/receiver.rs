@@ -0,0 +1,329 @@
+use std::{
+    io::{ErrorKind, Read},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::{Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use thread_safe_utils::signal::CancellationToken;
+
+use super::{FrameFormat, MAX_FRAME_LEN, NetworkTarget, read_frame};
+use crate::{LogMessage, Logger, severity::LogSeverity};
+
+type SharedLogger = Arc<Mutex<Box<dyn Logger + Send>>>;
+
+/// How long to wait before accepting or receiving again after a failure, so running out of file
+/// handles doesn't become a busy loop
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(50);
+/// How often a receiver with nothing to do checks whether it has been cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+enum Listener {
+    Tcp(TcpListener),
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+/// A connection that can be read on one thread and shut down from another
+trait Connection: Read + Send + Sized + 'static {
+    fn try_clone(&self) -> std::io::Result<Self>;
+    fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()>;
+    fn shutdown(&self) -> std::io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        std::os::unix::net::UnixStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+/// Accepts frames sent by [`NetworkLogger`](super::NetworkLogger)s and passes the decoded messages to
+/// a local logger. Stream connections are each read on their own thread.
+pub struct LogReceiver {
+    listener: Listener,
+    format: FrameFormat,
+    cancel: CancellationToken,
+}
+
+impl LogReceiver {
+    pub fn bind(target: &NetworkTarget, format: FrameFormat) -> Result<Self, std::io::Error> {
+        let listener = match target {
+            NetworkTarget::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr)?),
+            NetworkTarget::Udp(addr) => Listener::Udp(UdpSocket::bind(addr)?),
+            #[cfg(unix)]
+            NetworkTarget::Unix(path) => {
+                // A socket file left behind by a previous receiver would make bind fail
+                if path.try_exists()? {
+                    std::fs::remove_file(path)?;
+                }
+                Listener::Unix(std::os::unix::net::UnixListener::bind(path)?)
+            }
+        };
+        Ok(Self {
+            listener,
+            format,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Stops the receiver once `token` is cancelled: it stops listening, closes every open
+    /// connection and [`run`](Self::run) returns.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    /// The address actually bound, useful when binding to port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            Listener::Udp(socket) => socket.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix(_) => None,
+        }
+    }
+
+    /// Receives messages into `logger` until cancelled, see
+    /// [`with_cancellation`](Self::with_cancellation).
+    ///
+    /// Failing to accept a connection or receive a datagram, and frames that can't be decoded,
+    /// are reported to `logger` and skipped. Only failing to set up the listener is returned.
+    pub fn run<L: Logger + Send + 'static>(self, logger: L) -> Result<(), std::io::Error> {
+        let logger: SharedLogger = Arc::new(Mutex::new(Box::new(logger)));
+        let (format, cancel) = (self.format, self.cancel);
+        match self.listener {
+            Listener::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                let accept = || listener.accept().map(|(stream, _)| stream);
+                Self::accept_connections(accept, format, &logger, &cancel);
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                let accept = || listener.accept().map(|(stream, _)| stream);
+                Self::accept_connections(accept, format, &logger, &cancel);
+            }
+            Listener::Udp(socket) => {
+                socket.set_read_timeout(Some(CANCEL_POLL_INTERVAL))?;
+                let mut buf = vec![0u8; (MAX_FRAME_LEN + 4).min(u16::MAX as usize)];
+                while !cancel.is_cancelled() {
+                    match socket.recv(&mut buf) {
+                        Ok(len) => match format.decode_frame(&buf[..len]) {
+                            Ok(message) => Self::deliver(&logger, &message),
+                            Err(e) => Self::warn(&logger, format!("Dropped a malformed log frame. {}", e)),
+                        },
+                        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                        // Such as a reset reported for an earlier datagram, the socket is still usable
+                        Err(e) => {
+                            Self::warn(&logger, format!("Failed to receive a log frame. {}", e));
+                            std::thread::sleep(ACCEPT_RETRY_DELAY);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the receiver on its own thread
+    pub fn spawn<L: Logger + Send + 'static>(
+        self,
+        logger: L,
+    ) -> JoinHandle<Result<(), std::io::Error>> {
+        std::thread::spawn(move || self.run(logger))
+    }
+
+    /// Accepts connections from a non-blocking listener until cancelled, then closes the ones
+    /// still open and waits for their readers
+    fn accept_connections<C: Connection>(
+        accept: impl Fn() -> std::io::Result<C>,
+        format: FrameFormat,
+        logger: &SharedLogger,
+        cancel: &CancellationToken,
+    ) {
+        let mut connections = Vec::new();
+        while !cancel.is_cancelled() {
+            let accepted = accept().and_then(|stream| Self::spawn_reader(stream, format, logger.clone()));
+            match accepted {
+                Ok(connection) => connections.push(connection),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(CANCEL_POLL_INTERVAL),
+                Err(e) => {
+                    Self::warn(logger, format!("Failed to accept a log connection. {}", e));
+                    std::thread::sleep(ACCEPT_RETRY_DELAY);
+                }
+            }
+            connections.retain(|(_, reader): &(C, JoinHandle<()>)| !reader.is_finished());
+        }
+        for (stream, reader) in connections {
+            let _ = stream.shutdown();
+            let _ = reader.join();
+        }
+    }
+
+    /// Passes `message` on, even if a panicking logger poisoned the lock
+    fn deliver(logger: &SharedLogger, message: &LogMessage) {
+        let mut logger = logger.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = logger.log(message);
+    }
+
+    /// Reports a problem with the receiver itself to `logger`
+    fn warn(logger: &SharedLogger, content: String) {
+        Self::deliver(logger, &LogMessage::new(LogSeverity::Warning, content).with_target(module_path!()));
+    }
+
+    /// Reads `stream` on a new thread, returning another handle to it that can shut it down
+    fn spawn_reader<C: Connection>(
+        stream: C,
+        format: FrameFormat,
+        logger: SharedLogger,
+    ) -> std::io::Result<(C, JoinHandle<()>)> {
+        // Accepted connections can inherit the listener's non-blocking mode
+        stream.set_nonblocking(false)?;
+        let handle = stream.try_clone()?;
+        let mut stream = stream;
+        let reader = std::thread::spawn(move || {
+            // A malformed frame ends the connection, there's no way to find the next frame boundary
+            loop {
+                match read_frame(&mut stream) {
+                    Ok(Some(payload)) => match format.decode(&payload) {
+                        Ok(message) => Self::deliver(&logger, &message),
+                        Err(e) => {
+                            let content = format!("Closed a log connection after a malformed frame. {}", e);
+                            Self::warn(&logger, content);
+                            break;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        Self::warn(&logger, format!("Closed a log connection after a failed read. {}", e));
+                        break;
+                    }
+                }
+            }
+        });
+        Ok((handle, reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        LogMessage,
+        loggers::ring::{LogQuery, RingBufferHandle, RingBufferLogger},
+        net::NetworkLogger,
+        severity::LogSeverity,
+    };
+
+    fn round_trip(target: NetworkTarget, format: FrameFormat) {
+        let receiver = LogReceiver::bind(&target, format).unwrap();
+        let target = match target {
+            NetworkTarget::Tcp(_) => NetworkTarget::Tcp(receiver.local_addr().unwrap()),
+            NetworkTarget::Udp(_) => NetworkTarget::Udp(receiver.local_addr().unwrap()),
+            #[cfg(unix)]
+            other => other,
+        };
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let cancel = CancellationToken::new();
+        let thread = receiver.with_cancellation(&cancel).spawn(ring);
+        let mut sink = NetworkLogger::connect(target, format).unwrap();
+        for i in 0..3 {
+            let message =
+                LogMessage::new(LogSeverity::Info, format!("Frame {}", i)).with_field("index", i);
+            sink.log(&message).unwrap();
+        }
+        wait_for(&handle, 3);
+        let messages = handle.query(&LogQuery::new());
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content, "Frame 2");
+        assert_eq!(messages[2].field("index"), Some("2"));
+
+        // Stops even with the sink still connected
+        cancel.cancel();
+        assert!(thread.join().unwrap().is_ok());
+    }
+
+    fn wait_for(handle: &RingBufferHandle, count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handle.len() < count && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn tcp_json() {
+        round_trip(
+            NetworkTarget::Tcp("127.0.0.1:0".parse().unwrap()),
+            FrameFormat::Json,
+        );
+    }
+
+    #[test]
+    fn udp_bincode() {
+        round_trip(
+            NetworkTarget::Udp("127.0.0.1:0".parse().unwrap()),
+            FrameFormat::Bincode,
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_bincode() {
+        let path = std::env::temp_dir().join(format!("logger_receiver_{}.sock", std::process::id()));
+        round_trip(NetworkTarget::Unix(path.clone()), FrameFormat::Bincode);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn udp_reports_malformed_frames_and_keeps_receiving() {
+        let target = NetworkTarget::Udp("127.0.0.1:0".parse().unwrap());
+        let receiver = LogReceiver::bind(&target, FrameFormat::Json).unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let cancel = CancellationToken::new();
+        let thread = receiver.with_cancellation(&cancel).spawn(ring);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(b"\0\0\0\x03abc", addr).unwrap();
+        let frame = FrameFormat::Json.encode(&LogMessage::new(LogSeverity::Info, "After")).unwrap();
+        socket.send_to(&frame, addr).unwrap();
+        wait_for(&handle, 2);
+        let messages = handle.query(&LogQuery::new());
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].severity, LogSeverity::Warning);
+        assert!(messages[0].content.starts_with("Dropped a malformed log frame"));
+        assert_eq!(messages[1].content, "After");
+
+        cancel.cancel();
+        assert!(thread.join().unwrap().is_ok());
+    }
+}
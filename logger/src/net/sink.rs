@@ -0,0 +1,110 @@
+use std::{
+    io::Write,
+    net::{TcpStream, UdpSocket},
+    time::Duration,
+};
+
+use super::{FrameFormat, NetworkTarget};
+use crate::{LogMessage, LogResult, Logger};
+
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum Connection {
+    Stream(Box<dyn Write + Send>),
+    Udp(UdpSocket),
+}
+
+/// Sends every message as a frame to a [`LogReceiver`](super::LogReceiver) or any other collector.
+///
+/// The connection is opened on the first message and reopened on the next message after a failure,
+/// so wrapping this in a [`MultiLogger`](crate::loggers::multi::MultiLogger) gives it backoff.
+/// Writes to a peer that stops reading fail after a timeout rather than holding up the logging
+/// thread.
+pub struct NetworkLogger {
+    target: NetworkTarget,
+    format: FrameFormat,
+    write_timeout: Duration,
+    connection: Option<Connection>,
+}
+
+impl Logger for NetworkLogger {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let frame = self.format.encode(message)?;
+        let res = match self.connection()? {
+            Connection::Stream(stream) => stream.write_all(&frame),
+            Connection::Udp(socket) => socket.send(&frame).map(|_| ()),
+        };
+        if res.is_err() {
+            self.connection = None;
+        }
+        Ok(res?)
+    }
+
+    fn flush(&mut self) -> LogResult {
+        if let Some(Connection::Stream(stream)) = &mut self.connection {
+            stream.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl NetworkLogger {
+    /// Creates a logger that connects to `target` when it first logs
+    pub fn new(target: NetworkTarget, format: FrameFormat) -> Self {
+        Self {
+            target,
+            format,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            connection: None,
+        }
+    }
+
+    /// How long a stream write may block before the message fails, 2 seconds by default. Applies
+    /// from the next connection.
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Creates a logger and connects to `target` immediately
+    pub fn connect(target: NetworkTarget, format: FrameFormat) -> Result<Self, std::io::Error> {
+        let mut logger = Self::new(target, format);
+        logger.connection = Some(logger.open()?);
+        Ok(logger)
+    }
+
+    fn open(&self) -> Result<Connection, std::io::Error> {
+        Ok(match &self.target {
+            NetworkTarget::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(self.write_timeout))?;
+                Connection::Stream(Box::new(stream))
+            }
+            NetworkTarget::Udp(addr) => {
+                let local = if addr.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                Connection::Udp(socket)
+            }
+            #[cfg(unix)]
+            NetworkTarget::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(self.write_timeout))?;
+                Connection::Stream(Box::new(stream))
+            }
+        })
+    }
+
+    fn connection(&mut self) -> Result<&mut Connection, std::io::Error> {
+        if self.connection.is_none() {
+            self.connection = Some(self.open()?);
+        }
+        // Just filled in above
+        Ok(self.connection.as_mut().unwrap())
+    }
+}
@@ -0,0 +1,271 @@
+//! Reading log files written with [`LogfmtFormatter`](crate::formatters::logfmt::LogfmtFormatter),
+//! such as a [`FileLogger`](crate::loggers::file::FileLogger) given that formatter, back into
+//! [`LogMessage`]s.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    iter::Peekable,
+    path::Path,
+};
+
+use chrono::{DateTime, Local};
+use thiserror::Error;
+
+use crate::{LogMessage, LogOrigin, SourceLocation, severity::LogSeverity};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseLineError {
+    #[error("Expected `key=value` at `{0}`")]
+    MissingValue(String),
+    #[error("Unterminated quoted value for `{0}`")]
+    Unterminated(String),
+    #[error("Missing `{0}`")]
+    MissingKey(&'static str),
+    #[error("Invalid {key} `{value}`")]
+    InvalidValue { key: &'static str, value: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ReadError {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error("Line {line}: {source}")]
+    Parse { line: usize, source: ParseLineError },
+}
+
+/// Splits a logfmt line into its pairs, undoing the quoting done when it was written
+fn split_pairs(line: &str) -> Result<Vec<(String, String)>, ParseLineError> {
+    let mut pairs = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while chars.peek().is_some() {
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some('=') if !key.is_empty() => break,
+                Some(c) if c != '=' && !c.is_whitespace() => key.push(c),
+                _ => return Err(ParseLineError::MissingValue(key)),
+            }
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some(c) => value.push(c),
+                        None => return Err(ParseLineError::Unterminated(key)),
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(ParseLineError::Unterminated(key)),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        pairs.push((key, value));
+    }
+    Ok(pairs)
+}
+
+/// Parses one line written by the logfmt formatter.
+///
+/// The built in keys are only taken from their first occurrence, so a field that happens to share
+/// a name with one of them is kept as a field.
+pub fn parse_line(line: &str) -> Result<LogMessage, ParseLineError> {
+    let mut time = None;
+    let mut severity = None;
+    let mut content = None;
+    let mut target = None;
+    let mut location = None;
+    let mut thread_id = None;
+    let mut component = None;
+    let mut process_id = None;
+    let mut fields = BTreeMap::new();
+    for (key, value) in split_pairs(line)? {
+        match key.as_str() {
+            "time" if time.is_none() => {
+                let parsed = DateTime::parse_from_rfc3339(&value)
+                    .map_err(|_| ParseLineError::InvalidValue { key: "time", value })?;
+                time = Some(parsed.with_timezone(&Local));
+            }
+            "level" if severity.is_none() => {
+                let parsed: LogSeverity = value
+                    .parse()
+                    .map_err(|_| ParseLineError::InvalidValue { key: "level", value })?;
+                severity = Some(parsed);
+            }
+            "msg" if content.is_none() => content = Some(value),
+            "target" if target.is_none() => target = Some(value),
+            "location" if location.is_none() => {
+                let parsed = value
+                    .rsplit_once(':')
+                    .and_then(|(file, line)| Some((file.to_string(), line.parse().ok()?)));
+                let Some((file, line)) = parsed else {
+                    return Err(ParseLineError::InvalidValue { key: "location", value });
+                };
+                location = Some(SourceLocation { file, line });
+            }
+            "thread" if thread_id.is_none() => {
+                let parsed = value
+                    .parse()
+                    .map_err(|_| ParseLineError::InvalidValue { key: "thread", value })?;
+                thread_id = Some(parsed);
+            }
+            "origin" if component.is_none() => component = Some(value),
+            "pid" if process_id.is_none() => {
+                let parsed = value
+                    .parse()
+                    .map_err(|_| ParseLineError::InvalidValue { key: "pid", value })?;
+                process_id = Some(parsed);
+            }
+            _ => {
+                fields.insert(key, value);
+            }
+        }
+    }
+    Ok(LogMessage {
+        time: time.ok_or(ParseLineError::MissingKey("time"))?,
+        severity: severity.ok_or(ParseLineError::MissingKey("level"))?,
+        content: content.ok_or(ParseLineError::MissingKey("msg"))?,
+        target,
+        location,
+        thread_id: thread_id.unwrap_or_default(),
+        fields,
+        origin: component.map(|component| LogOrigin {
+            process_id: process_id.unwrap_or_default(),
+            component,
+        }),
+    })
+}
+
+/// Yields every message in a logfmt log, skipping blank lines
+pub struct LogReader<R: BufRead> {
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl LogReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> LogReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<LogMessage, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line += 1;
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if !line.trim().is_empty() {
+                return Some(parse_line(&line).map_err(|source| ReadError::Parse {
+                    line: self.line,
+                    source,
+                }));
+            }
+        }
+    }
+}
+
+/// Interleaves several logs into one timeline, see [`merge`]
+pub struct MergedLogs<I: Iterator> {
+    sources: Vec<Peekable<I>>,
+}
+
+/// Merges logs that are each in chronological order (such as the host's and the payload's) by
+/// message time. Errors are passed on as soon as their source reaches them.
+pub fn merge<I, E>(sources: impl IntoIterator<Item = I>) -> MergedLogs<I>
+where
+    I: Iterator<Item = Result<LogMessage, E>>,
+{
+    MergedLogs {
+        sources: sources.into_iter().map(Iterator::peekable).collect(),
+    }
+}
+
+impl<I, E> Iterator for MergedLogs<I>
+where
+    I: Iterator<Item = Result<LogMessage, E>>,
+{
+    type Item = Result<LogMessage, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut earliest: Option<(usize, DateTime<Local>)> = None;
+        for (index, source) in self.sources.iter_mut().enumerate() {
+            match source.peek() {
+                Some(Err(_)) => return source.next(),
+                Some(Ok(message)) if earliest.is_none_or(|(_, time)| message.time < time) => {
+                    earliest = Some((index, message.time));
+                }
+                _ => {}
+            }
+        }
+        self.sources[earliest?.0].next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::formatters::{LogFormatter, logfmt::LogfmtFormatter};
+
+    #[test]
+    fn logfmt_round_trip_and_merge() {
+        let formatter = LogfmtFormatter::new();
+        let message = LogMessage::new(LogSeverity::Warning, "Line one\n\"quoted\" \\ =")
+            .with_target("client::slave")
+            .with_location("client/src/slave/mod.rs", 42)
+            .with_origin(LogOrigin::new("payload"))
+            .with_field("msg", "a field named msg")
+            .with_field("count", 3);
+        let parsed = parse_line(&formatter.format(&message)).unwrap();
+        assert_eq!(parsed.content, message.content);
+        assert_eq!(parsed.severity, message.severity);
+        assert_eq!(parsed.time, message.time);
+        assert_eq!(parsed.target, message.target);
+        assert_eq!(parsed.location, message.location);
+        assert_eq!(parsed.thread_id, message.thread_id);
+        assert_eq!(parsed.origin, message.origin);
+        assert_eq!(parsed.fields, message.fields);
+
+        let first = LogMessage::new(LogSeverity::Info, "first");
+        let mut second = LogMessage::new(LogSeverity::Info, "second");
+        second.time = first.time + TimeDelta::milliseconds(1);
+        let mut third = LogMessage::new(LogSeverity::Info, "third");
+        third.time = first.time + TimeDelta::milliseconds(2);
+        let host = format!("{}\n\n{}\n", formatter.format(&first), formatter.format(&third));
+        let payload = format!("{}\nnot logfmt\n", formatter.format(&second));
+        let merged = merge([LogReader::new(host.as_bytes()), LogReader::new(payload.as_bytes())])
+            .collect::<Vec<_>>();
+        let contents = merged
+            .iter()
+            .filter_map(|m| m.as_ref().ok().map(|m| m.content.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(contents, ["first", "second", "third"]);
+        let errors = merged.iter().filter_map(|m| m.as_ref().err()).collect::<Vec<_>>();
+        assert!(matches!(errors[..], [ReadError::Parse { line: 2, .. }]));
+    }
+}
@@ -1,7 +1,7 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize};
-
+use thiserror::Error;
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd)]
@@ -26,3 +26,23 @@ impl Display for LogSeverity {
     }
 }
 
+
+#[derive(Error, Debug, PartialEq)]
+#[error("Unknown log severity `{0}`")]
+pub struct ParseSeverityError(pub String);
+
+impl FromStr for LogSeverity {
+    type Err = ParseSeverityError;
+
+    /// Accepts the severity names case-insensitively, plus `warn` and `trace` as aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(LogSeverity::Error),
+            "warn" | "warning" => Ok(LogSeverity::Warning),
+            "info" => Ok(LogSeverity::Info),
+            "debug" => Ok(LogSeverity::Debug),
+            "verbose" | "trace" => Ok(LogSeverity::Verbose),
+            _ => Err(ParseSeverityError(s.to_string())),
+        }
+    }
+}
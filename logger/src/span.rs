@@ -0,0 +1,146 @@
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    time::Instant,
+};
+
+use crate::{severity::LogSeverity, LogMessage, LogWorker};
+
+thread_local! {
+    /// IDs and names of the spans currently open on this thread, outermost first
+    static SPAN_STACK: RefCell<Vec<(u64, String)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_SPAN_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The `outer > inner` path of the spans open on the current thread, if there are any
+pub(crate) fn current_span_path() -> Option<String> {
+    SPAN_STACK.with_borrow(|stack| {
+        (!stack.is_empty()).then(|| stack.iter().map(|(_, name)| name.as_str()).collect::<Vec<_>>().join(" > "))
+    })
+}
+
+/// A guard that logs when a section of code starts and finishes, along with how long it took.
+///
+/// Spans nest per thread: anything logged through a [`LogWorker`] while a span is open gets a
+/// `span` field naming every open span. A span has to be dropped on the thread that opened it, so
+/// it isn't `Send`.
+///
+/// Usually opened with [`log_span!`](crate::log_span), which logs under the calling module.
+#[must_use = "the span is exited as soon as it is dropped"]
+pub struct Span {
+    /// Tells this span's stack entry apart from others with the same name
+    id: u64,
+    worker: LogWorker,
+    target: String,
+    name: String,
+    severity: LogSeverity,
+    start: Instant,
+    fields: Vec<(String, String)>,
+    /// Exiting pops this thread's span stack
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl Span {
+    pub(crate) fn enter(
+        worker: LogWorker,
+        target: impl Into<String>,
+        name: impl Into<String>,
+        severity: LogSeverity,
+    ) -> Self {
+        let (target, name) = (target.into(), name.into());
+        let id = NEXT_SPAN_ID.replace(NEXT_SPAN_ID.get().wrapping_add(1));
+        SPAN_STACK.with_borrow_mut(|stack| stack.push((id, name.clone())));
+        let _ = worker.log(
+            LogMessage::new(severity.clone(), format!("Entering {}", name)).with_target(target.clone()),
+        );
+        Self {
+            id,
+            worker,
+            target,
+            name,
+            severity,
+            start: Instant::now(),
+            fields: Vec::new(),
+            _thread_bound: PhantomData,
+        }
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.start.elapsed()
+    }
+
+    /// Attaches a field to the message logged when the span exits
+    pub fn record(&mut self, key: impl Into<String>, value: impl std::fmt::Display) {
+        self.fields.push((key.into(), value.to_string()));
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let mut message = LogMessage::new(
+            self.severity.clone(),
+            format!("Finished {} in {:.3?}", self.name, elapsed),
+        )
+        .with_target(self.target.clone())
+        .with_field("elapsed_ms", format!("{:.3}", elapsed.as_secs_f64() * 1000.0));
+        for (key, value) in self.fields.drain(..) {
+            message = message.with_field(key, value);
+        }
+        // Logged before popping so the exit message still carries this span's path
+        let _ = self.worker.log(message);
+        SPAN_STACK.with_borrow_mut(|stack| {
+            if let Some(pos) = stack.iter().rposition(|(id, _)| *id == self.id) {
+                stack.remove(pos);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        log_span,
+        loggers::ring::{LogQuery, RingBufferLogger},
+        severity::LogSeverity,
+        LogManager, LogMessage,
+    };
+
+    #[test]
+    fn spans_nest_and_time() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let manager = LogManager::new(ring);
+        {
+            let _outer = log_span!(manager, "scan");
+            let mut inner = manager.span_with_severity("logger::walk", "walk", LogSeverity::Info);
+            inner.record("entries", 3);
+        }
+        assert!(manager.flush(None));
+        let messages = handle.query(&LogQuery::new());
+        let spans = messages.iter().map(|m| m.field("span")).collect::<Vec<_>>();
+        assert_eq!(spans, [Some("scan"), Some("scan > walk"), Some("scan > walk"), Some("scan")]);
+        assert_eq!(messages[2].field("entries"), Some("3"));
+        let targets = messages.iter().map(|m| m.target.as_deref()).collect::<Vec<_>>();
+        assert_eq!(targets, [Some(module_path!()), Some("logger::walk"), Some("logger::walk"), Some(module_path!())]);
+        assert!(messages[3].field("elapsed_ms").is_some());
+        assert!(manager.time_since_start() >= chrono::TimeDelta::zero());
+    }
+
+    #[test]
+    fn spans_dropped_out_of_order_leave_the_right_path() {
+        let ring = RingBufferLogger::new(10);
+        let handle = ring.handle();
+        let manager = LogManager::new(ring);
+        let first = log_span!(manager, "walk");
+        let second = log_span!(manager, "send");
+        let third = log_span!(manager, "walk");
+        drop(first);
+        manager.log(LogMessage::new(LogSeverity::Info, "still walking"));
+        drop(third);
+        drop(second);
+        assert!(manager.flush(None));
+        let logged = handle.query(&LogQuery::new().with_text("still walking"));
+        assert_eq!(logged[0].field("span"), Some("send > walk"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use thread_safe_utils::queue::ThreadSafeQueue;
+
+use crate::{LogMessage, severity::LogSeverity};
+
+struct Subscriber {
+    id: usize,
+    severity: LogSeverity,
+    queue: ThreadSafeQueue<LogMessage>,
+    dropped: Arc<AtomicUsize>,
+}
+
+#[derive(Default)]
+struct SubscriberList {
+    next_id: usize,
+    subscribers: Vec<Subscriber>,
+    closed: bool,
+}
+
+/// The subscribers of a [`LogManager`](crate::LogManager), shared between the manager, its logging
+/// thread and every [`LogSubscription`]
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers {
+    list: Arc<Mutex<SubscriberList>>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(&self, severity: LogSeverity, capacity: usize) -> LogSubscription {
+        let queue = ThreadSafeQueue::bounded(capacity);
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut id = 0;
+        if let Ok(mut list) = self.list.lock() {
+            if list.closed {
+                queue.close();
+            } else {
+                id = list.next_id;
+                list.next_id += 1;
+                list.subscribers.push(Subscriber {
+                    id,
+                    severity,
+                    queue: queue.clone(),
+                    dropped: dropped.clone(),
+                });
+            }
+        }
+        LogSubscription {
+            id,
+            queue,
+            dropped,
+            subscribers: self.clone(),
+        }
+    }
+
+    fn unsubscribe(&self, id: usize) {
+        if let Ok(mut list) = self.list.lock() {
+            list.subscribers.retain(|s| s.id != id);
+        }
+    }
+
+    /// Hands `message` to every interested subscriber without ever waiting on them. A subscriber
+    /// whose queue is full misses the message.
+    pub(crate) fn publish(&self, message: &LogMessage) {
+        let Ok(list) = self.list.lock() else {
+            return;
+        };
+        for subscriber in list.subscribers.iter().filter(|s| message.severity <= s.severity) {
+            if subscriber.queue.try_enqueue(message.clone()).is_err() {
+                subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Ends every subscription once it has received what was already published, and stops
+    /// accepting new ones
+    pub(crate) fn close(&self) {
+        if let Ok(mut list) = self.list.lock() {
+            list.closed = true;
+            for subscriber in list.subscribers.drain(..) {
+                subscriber.queue.close();
+            }
+        }
+    }
+}
+
+/// A live feed of messages from a [`LogManager`](crate::LogManager), created with
+/// [`LogManager::subscribe`](crate::LogManager::subscribe). Dropping it unsubscribes.
+pub struct LogSubscription {
+    id: usize,
+    queue: ThreadSafeQueue<LogMessage>,
+    dropped: Arc<AtomicUsize>,
+    subscribers: Subscribers,
+}
+
+impl Drop for LogSubscription {
+    fn drop(&mut self) {
+        self.subscribers.unsubscribe(self.id);
+    }
+}
+
+impl LogSubscription {
+    /// Blocks until a message arrives. Returns `None` once the manager has shut down and every
+    /// message published before that has been received.
+    pub fn recv(&self) -> Option<LogMessage> {
+        self.queue.dequeue().ok()
+    }
+
+    /// Like [`recv`](Self::recv), but awaits the message instead of blocking. Works on any executor.
+    pub async fn recv_async(&self) -> Option<LogMessage> {
+        self.queue.dequeue_async().await.ok()
+    }
+
+    pub fn try_recv(&self) -> Option<LogMessage> {
+        self.queue.try_dequeue()
+    }
+
+    pub fn recv_timeout(&self, dur: Duration) -> Option<LogMessage> {
+        self.queue.try_dequeue_timeout(dur).ok().flatten()
+    }
+
+    /// Messages this subscriber missed because it wasn't keeping up
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn unsubscribe(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{loggers::null::NullLogger, severity::LogSeverity, LogManager, LogMessage};
+
+    #[test]
+    fn subscribers_filter_and_drop_without_blocking() {
+        let manager = LogManager::new(NullLogger::new());
+        let warnings = manager.subscribe(LogSeverity::Warning);
+        let small = manager.subscribe_with_capacity(LogSeverity::Verbose, 2);
+        for sev in [LogSeverity::Info, LogSeverity::Error, LogSeverity::Debug, LogSeverity::Warning] {
+            manager.log(LogMessage::new(sev, "message"));
+        }
+        assert!(manager.flush(None));
+        assert_eq!(warnings.try_recv().map(|m| m.severity), Some(LogSeverity::Error));
+        assert_eq!(warnings.try_recv().map(|m| m.severity), Some(LogSeverity::Warning));
+        assert!(warnings.try_recv().is_none());
+        assert_eq!(small.dropped(), 2);
+        drop(warnings);
+        drop(manager);
+        assert_eq!(small.recv().map(|m| m.severity), Some(LogSeverity::Info));
+        assert_eq!(small.recv().map(|m| m.severity), Some(LogSeverity::Error));
+        assert!(small.recv().is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ops::DerefMut, thread};
+use std::{ops::DerefMut, path::PathBuf};
 
 use client::{
     control::{command::Instruction, message::Message},
@@ -6,7 +6,11 @@ use client::{
 };
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use lazy_static::lazy_static;
-use std::io::Write;
+use logger::{
+    emergency,
+    loggers::file::{FileConflictBehavior, FileLogger},
+    LogMessage, LogResult, Logger,
+};
 use thread_safe_utils::signal::{Signal, SignallableData};
 use windows::Win32::{
     Foundation::{FreeLibrary, HANDLE, HMODULE},
@@ -32,33 +36,69 @@ lazy_static! {
     static ref PARAMS: SignallableData<RuntimeStorage> = Default::default();
 }
 
-fn log_to_temp_console(msg: impl Into<String>) {
-    let mut idk = std::process::Command::new("cmd");
-    let formatted_msg = msg
-        .into()
-        .lines()
-        .map(|line| format!("echo {}", line))
-        .collect::<Vec<_>>()
-        .join(" & ");
-    let _ = idk.arg("/K").arg(formatted_msg).spawn();
+/// Pops up a console window showing the message, since the DLL has no console of its own
+struct TempConsoleLogger;
+
+impl Logger for TempConsoleLogger {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        let content = match &message.location {
+            Some(location) => format!("{} at {}", message.content, location),
+            None => message.content.clone(),
+        };
+        let formatted_msg = content
+            .lines()
+            .map(|line| format!("echo {}", line))
+            .collect::<Vec<_>>()
+            .join(" & ");
+        std::process::Command::new("cmd")
+            .arg("/K")
+            .arg(formatted_msg)
+            .spawn()?;
+        Ok(())
+    }
+}
+
+/// Where the payload's log files are written, shared by the Slave's log and the crash log
+fn log_dir() -> PathBuf {
+    std::env::temp_dir().join("r6-tracker-injector")
+}
+
+/// Appends to a file it only opens once something is logged, so attaching the DLL doesn't create
+/// or touch the file unless there is a crash to record
+struct LazyFileLogger {
+    path: PathBuf,
+    file: Option<FileLogger>,
 }
 
-fn log_to_file(msg: impl AsRef<str>) {
-    if let Ok(mut file) = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(r"C:\Users\braed\Documents\GitHub\r6-tracker-injector\dll_crash.txt")
-    {
-        let _ = writeln!(file, "{}", msg.as_ref());
+impl Logger for LazyFileLogger {
+    fn log(&mut self, message: &LogMessage) -> LogResult {
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            self.file = Some(FileLogger::new(self.path.clone(), FileConflictBehavior::Append)?);
+        }
+        match &mut self.file {
+            Some(file) => file.log(message),
+            None => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> LogResult {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
     }
 }
 
 pub fn set_panic_hook() {
-    std::panic::set_hook(Box::new(|info| {
-        let msg = format!("Panic: {}", info);
-        log_to_file(msg.clone());
-        log_to_temp_console(msg);
-    }));
+    emergency::add_sink(LazyFileLogger {
+        path: log_dir().join("dll_crash.txt"),
+        file: None,
+    });
+    emergency::add_sink(TempConsoleLogger);
+    emergency::install_panic_hook();
 }
 
 #[no_mangle]
@@ -82,11 +122,9 @@ unsafe extern "system" fn code_runner(_ptr: *mut std::ffi::c_void) -> u32 {
     let storage = lock.deref_mut();
     let tx = storage.sender.take().unwrap();
     let rx = storage.receiver.take().unwrap();
-    let client = Slave::new(
-        tx,
-        rx,
-        r"C:\Users\braed\Documents\GitHub\r6-tracker-injector\dll.log",
-    );
+    let log_dir = log_dir();
+    let _ = std::fs::create_dir_all(&log_dir);
+    let client = Slave::new(tx, rx, log_dir.join("dll.log"));
     let _ = client.run_client();
     drop(client);
     FreeLibraryAndExitThread(lock.current_module, 0);
@@ -10,11 +10,21 @@ use device_query::{DeviceEvents, DeviceEventsHandler, Keycode};
 use dll_syringe::{process::OwnedProcess, Syringe};
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use logger::{
-    loggers::{console::ConsoleLogger, filter::LogFilter, null::NullLogger},
+    formatters::{logfmt::LogfmtFormatter, text::TextFormatter},
+    log_info,
+    loggers::{
+        console::ConsoleLogger,
+        dedup::DuplicateFilter,
+        file::{FileConflictBehavior, FileLogger, RotationPolicy},
+        filter::{FilterDirectives, LogFilter},
+        multi::MultiLogger,
+        null::NullLogger,
+    },
     severity::LogSeverity,
-    LogManager, LogMessage,
+    LogManager,
 };
 use siege::MatchData;
+use thread_safe_utils::signal::CancellationToken;
 use std::{collections::HashMap, fs::File, io::{BufReader, BufWriter, Stdin}};
 use std::fs;
 use std::path::Path;
@@ -26,6 +36,15 @@ use std::io::{self, Read};
 mod client_info;
 
 static DLL_PATH: &str = "deps/payload.dll";
+/// Filter directives for the host's console, e.g. `client::master=info,*=warn`
+static LOG_FILTER_ENV: &str = "R6_LOG";
+/// Path of a JSON logger config (see `logger::config`) replacing the default console and file
+/// pipeline
+static LOG_CONFIG_ENV: &str = "R6_LOG_CONFIG";
+/// Directory under the temp directory holding the host's log, next to the payload's `dll.log`
+static LOG_DIR: &str = "r6-tracker-injector";
+const LOG_FILE_MAX_SIZE: u64 = 16 * 1024 * 1024;
+const LOG_FILES_KEPT: usize = 5;
 
 fn setup(path: impl AsRef<Path>) -> Option<(IpcSender<Instruction>, IpcReceiver<Message>)> {
     if let Some(target_process) = OwnedProcess::find_first_by_name("Overwolf.exe") {
@@ -102,17 +121,57 @@ fn main() {
     path.pop();
     path.push(DLL_PATH);
     if let Some((sender, receiver)) = setup(&path) {
-        let console_logger = ConsoleLogger::new();
-        //let console_logger = NullLogger::new();
-        let log_manager = LogManager::new(LogFilter::new(LogSeverity::Debug, console_logger));
+        let log_manager = match std::env::var(LOG_CONFIG_ENV) {
+            Ok(config) => logger::config::load(&config).unwrap_or_else(|e| {
+                println!("Ignoring {}: {}", LOG_CONFIG_ENV, e);
+                default_log_manager()
+            }),
+            Err(_) => default_log_manager(),
+        };
+        let _ = log_manager.install_log_bridge(LogSeverity::Info);
 
-        let master = Arc::new(Master::new(sender, receiver, log_manager.get_log_worker()));
+        // Cancelled once the payload stops responding, taking the IPC and logging threads with it
+        let shutdown = CancellationToken::new();
+        log_manager.stop_on(&shutdown);
+        let master = Arc::new(Master::new(sender, receiver, log_manager.get_log_worker(), &shutdown));
 
         let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
         let _guard = device_events.on_key_down(generate_keybinds_callback(master.clone()));
 
         master_loop(master, log_manager);
+        shutdown.cancel();
+    }
+}
+
+fn default_log_manager() -> LogManager {
+    let console_logger = ConsoleLogger::new().with_formatter(
+        TextFormatter::new()
+            .with_time_format("%I:%M:%S%p")
+            .with_origin_colours(),
+    );
+    //let console_logger = NullLogger::new();
+    let directives = FilterDirectives::from_env(LOG_FILTER_ENV, LogSeverity::Debug)
+        .unwrap_or_else(|e| {
+            println!("Ignoring {}: {}", LOG_FILTER_ENV, e);
+            FilterDirectives::new(LogSeverity::Debug)
+        });
+    let mut loggers = MultiLogger::new()
+        .with_named_logger("console", LogFilter::with_directives(directives, console_logger));
+    // Written as logfmt so `logq` can merge it with the payload's log
+    match open_log_file() {
+        Ok(file) => loggers = loggers.with_named_logger("file", file.with_formatter(LogfmtFormatter::new())),
+        Err(e) => println!("Not writing a log file: {}", e),
     }
+    LogManager::new(DuplicateFilter::new(loggers)).with_origin("host")
+}
+
+fn open_log_file() -> Result<FileLogger, io::Error> {
+    let dir = std::env::temp_dir().join(LOG_DIR);
+    fs::create_dir_all(&dir)?;
+    let policy = RotationPolicy::new()
+        .with_max_size(LOG_FILE_MAX_SIZE)
+        .with_keep(LOG_FILES_KEPT);
+    FileLogger::with_rotation(dir.join("host.log"), FileConflictBehavior::RenameOld, policy)
 }
 
 fn load_latest_json(client_info: &mut ClientInfo) {
@@ -164,17 +223,11 @@ fn master_loop(master: Arc<Master>, log_manager: LogManager) {
         match res {
             DataMessage::ProcessId(id) => {
                 client_info.set_process_id(id);
-                let _ = log_manager.log(LogMessage::new(
-                    LogSeverity::Info,
-                    format!("Returned process ID: {}", id),
-                ));
+                let _ = log_info!(log_manager, { process_id = id }, "Returned process ID");
             }
             DataMessage::ThreadId(id) => {
                 client_info.set_thread_id(id);
-                let _ = log_manager.log(LogMessage::new(
-                    LogSeverity::Info,
-                    format!("Returned thread ID: {}", id),
-                ));
+                let _ = log_info!(log_manager, { thread_id = id }, "Returned thread ID");
             }
             DataMessage::Json(items) => {
                 println!("Located {} json elements!", items.len());
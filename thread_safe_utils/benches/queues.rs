@@ -0,0 +1,98 @@
+//! Compares [`ThreadSafeQueue`] and [`RingQueue`] moving IPC-sized and log-sized messages.
+//!
+//! Run with `cargo bench -p thread_safe_utils`. Set `QUEUE_BENCH_MESSAGES` to change how many
+//! messages each case moves.
+
+use std::{
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use thread_safe_utils::queue::{RingQueue, SharedQueue, ThreadSafeQueue};
+
+const CAPACITY: usize = 1024;
+const PRODUCERS: usize = 4;
+
+/// Roughly one serialised IPC message
+type IpcMessage = [u8; 64];
+
+/// Roughly one queued log message: some text plus the fields alongside it
+#[derive(Clone)]
+struct LogMessage {
+    _text: String,
+    _target: String,
+    _time: Duration,
+    _severity: u8,
+}
+
+fn log_message() -> LogMessage {
+    LogMessage {
+        _text: "x".repeat(160),
+        _target: String::from("client::master"),
+        _time: Duration::from_secs(1),
+        _severity: 2,
+    }
+}
+
+/// Moves `messages` copies of `message` from `producers` threads to one consumer, returning the
+/// time per message
+fn run<T: Clone + Send + 'static>(
+    queue: SharedQueue<T>,
+    producers: usize,
+    messages: usize,
+    message: T,
+) -> Duration {
+    let per_producer = messages / producers;
+    let start = Instant::now();
+    let threads = (0..producers)
+        .map(|_| {
+            let (queue, message) = (queue.clone(), message.clone());
+            std::thread::spawn(move || {
+                for _ in 0..per_producer {
+                    queue.enqueue(message.clone()).unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..per_producer * producers {
+        black_box(queue.dequeue().unwrap());
+    }
+    let elapsed = start.elapsed();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    elapsed / (per_producer * producers) as u32
+}
+
+fn compare<T: Clone + Send + 'static>(name: &str, messages: usize, message: T) {
+    for producers in [1, PRODUCERS] {
+        let locked = run(
+            Arc::new(ThreadSafeQueue::bounded(CAPACITY)),
+            producers,
+            messages,
+            message.clone(),
+        );
+        let ring = run(
+            Arc::new(RingQueue::bounded(CAPACITY)),
+            producers,
+            messages,
+            message.clone(),
+        );
+        println!(
+            "{name:<4} {producers} producer(s): ThreadSafeQueue {:>6} ns/msg, RingQueue {:>6} ns/msg",
+            locked.as_nanos(),
+            ring.as_nanos()
+        );
+    }
+}
+
+fn main() {
+    let messages = std::env::var("QUEUE_BENCH_MESSAGES")
+        .ok()
+        .and_then(|messages| messages.parse().ok())
+        .unwrap_or(1_000_000);
+    let ipc: IpcMessage = [0; 64];
+    compare("ipc", messages, ipc);
+    compare("log", messages, log_message());
+}
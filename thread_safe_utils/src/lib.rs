@@ -1,2 +1,3 @@
+pub mod pool;
 pub mod queue;
-pub mod signal;
\ No newline at end of file
+pub mod signal;
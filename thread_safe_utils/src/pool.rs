@@ -0,0 +1,231 @@
+use std::{
+    any::Any,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+    queue::ThreadSafeQueue,
+    signal::{Signal, SignallableData},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+type ResultSlot<R> = Arc<SignallableData<Option<Result<R, PoolError>>>>;
+
+#[derive(Error, Debug)]
+pub enum PoolError {
+    #[error("The job panicked. {0}")]
+    Panicked(String),
+    /// The pool was shut down with [`ThreadPool::shutdown_now`] before the job started
+    #[error("The job was cancelled before it ran.")]
+    Cancelled,
+    #[error("The pool has been shut down.")]
+    ShutDown,
+    #[error("The job's result was lost to a poisoned mutex.")]
+    MutexPoison,
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("Unknown panic payload"))
+}
+
+/// Hands a job's result to its [`JobHandle`], or [`PoolError::Cancelled`] if the job is dropped
+/// without running
+struct Completion<R> {
+    slot: ResultSlot<R>,
+}
+
+impl<R> Completion<R> {
+    fn complete(self, result: Result<R, PoolError>) {
+        if let Ok(mut lock) = self.slot.lock() {
+            *lock = Some(result);
+        }
+    }
+}
+
+impl<R> Drop for Completion<R> {
+    fn drop(&mut self) {
+        if let Ok(mut lock) = self.slot.lock()
+            && lock.is_none()
+        {
+            *lock = Some(Err(PoolError::Cancelled));
+        }
+        self.slot.set_signal(true);
+    }
+}
+
+/// Waits for the result of a job given to a [`ThreadPool`]. Dropping it detaches the job.
+pub struct JobHandle<R> {
+    slot: ResultSlot<R>,
+}
+
+impl<R> JobHandle<R> {
+    pub fn is_finished(&self) -> bool {
+        self.slot.is_signalled()
+    }
+
+    /// Blocks until the job has run, returning what it returned or why it didn't
+    pub fn join(self) -> Result<R, PoolError> {
+        let mut lock = self.slot.lock_wait_for_signal().map_err(|_| PoolError::MutexPoison)?;
+        lock.take().unwrap_or(Err(PoolError::MutexPoison))
+    }
+
+    /// Like [`join`](Self::join), but hands the handle back if the job hasn't finished within `dur`
+    pub fn join_timeout(self, dur: Duration) -> Result<Result<R, PoolError>, Self> {
+        let lock = self.slot.lock_wait_while_timeout(dur, |_, finished| !finished);
+        match lock {
+            Ok(Some(mut lock)) => Ok(lock.take().unwrap_or(Err(PoolError::MutexPoison))),
+            Ok(None) => {
+                drop(lock);
+                Err(self)
+            }
+            Err(_) => Ok(Err(PoolError::MutexPoison)),
+        }
+    }
+}
+
+/// A fixed number of threads running jobs from a shared [`ThreadSafeQueue`].
+///
+/// A job that panics fails with [`PoolError::Panicked`] and leaves its thread running. Dropping
+/// the pool is the same as [`shutdown`](Self::shutdown).
+pub struct ThreadPool {
+    queue: ThreadSafeQueue<Job>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> Self {
+        Self::with_queue(threads, ThreadSafeQueue::new())
+    }
+
+    /// Creates a pool that holds at most `capacity` jobs waiting to run. [`spawn`](Self::spawn)
+    /// waits for room while it is full.
+    pub fn bounded(threads: usize, capacity: usize) -> Self {
+        Self::with_queue(threads, ThreadSafeQueue::bounded(capacity))
+    }
+
+    fn with_queue(threads: usize, queue: ThreadSafeQueue<Job>) -> Self {
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    // Closing lets the workers finish the queue, cancelling stops them after their
+                    // current job
+                    while let Ok(job) = queue.dequeue() {
+                        job();
+                    }
+                })
+            })
+            .collect();
+        Self { queue, workers }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Jobs waiting for a thread
+    pub fn queued(&self) -> usize {
+        self.queue.elements()
+    }
+
+    /// Queues `job` to run on the first free thread. Fails if the pool has been shut down.
+    pub fn spawn<F, R>(&self, job: F) -> Result<JobHandle<R>, PoolError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let slot: ResultSlot<R> = Arc::new(SignallableData::new(None));
+        let completion = Completion { slot: slot.clone() };
+        let job: Job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(job))
+                .map_err(|payload| PoolError::Panicked(panic_message(payload)));
+            completion.complete(result);
+        });
+        self.queue.enqueue(job).map_err(|_| PoolError::ShutDown)?;
+        Ok(JobHandle { slot })
+    }
+
+    /// Runs every job already queued, then stops the threads. Nothing more can be spawned.
+    pub fn shutdown(&mut self) {
+        self.queue.close();
+        self.join_workers();
+    }
+
+    /// Stops the threads once their current jobs finish. Jobs still queued are dropped, their
+    /// handles failing with [`PoolError::Cancelled`].
+    pub fn shutdown_now(&mut self) {
+        self.queue.cancel();
+        self.join_workers();
+        while let Some(job) = self.queue.try_dequeue() {
+            drop(job);
+        }
+    }
+
+    fn join_workers(&mut self) {
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_errors_and_shutdown_modes() {
+        let mut pool = ThreadPool::new(2);
+        let squares = (0..8u64).map(|n| pool.spawn(move || n * n).unwrap()).collect::<Vec<_>>();
+        let panicked = pool.spawn(|| -> u64 { panic!("scan failed") }).unwrap();
+        let after = pool.spawn(|| 1u64).unwrap();
+        let squares = squares.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+        assert_eq!(squares, [0, 1, 4, 9, 16, 25, 36, 49]);
+        assert!(matches!(panicked.join(), Err(PoolError::Panicked(message)) if message == "scan failed"));
+        assert!(after.join().is_ok_and(|n| n == 1));
+
+        let queued = (0..4)
+            .map(|_| pool.spawn(|| std::thread::sleep(Duration::from_millis(5))).unwrap())
+            .collect::<Vec<_>>();
+        pool.shutdown();
+        assert!(queued.into_iter().all(|handle| handle.join().is_ok()));
+        assert!(matches!(pool.spawn(|| ()), Err(PoolError::ShutDown)));
+
+        let mut pool = ThreadPool::new(1);
+        let started = Arc::new(SignallableData::new(()));
+        let release = Arc::new(SignallableData::new(()));
+        let running = {
+            let (started, release) = (started.clone(), release.clone());
+            pool.spawn(move || {
+                started.set_signal(true);
+                let _ = release.wait_for_signal();
+            })
+            .unwrap()
+        };
+        let abandoned = pool.spawn(|| ()).unwrap();
+        let _ = started.wait_for_signal();
+        let abandoned = abandoned.join_timeout(Duration::from_millis(10)).err().unwrap();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            release.set_signal(true);
+        });
+        pool.shutdown_now();
+        releaser.join().unwrap();
+        assert!(running.join().is_ok());
+        assert!(matches!(abandoned.join(), Err(PoolError::Cancelled)));
+    }
+}
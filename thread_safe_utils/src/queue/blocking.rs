@@ -0,0 +1,134 @@
+use std::{sync::Arc, time::Duration};
+
+use super::{EnqueueError, RingQueue, ThreadSafeQueue, ThreadSafeQueueError};
+
+/// The operations [`ThreadSafeQueue`] and [`RingQueue`] share, so code holding a
+/// [`SharedQueue`] works with whichever it is given. Each method behaves as the queues' own
+/// method of the same name.
+pub trait BlockingQueue<T>: Send + Sync {
+    /// The most elements the queue holds, `None` if it is unbounded
+    fn capacity(&self) -> Option<usize>;
+    fn elements(&self) -> usize;
+    fn enqueue(&self, data: T) -> Result<(), ThreadSafeQueueError>;
+    fn try_enqueue(&self, data: T) -> Result<(), EnqueueError<T>>;
+    fn enqueue_timeout(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>>;
+    fn force_enqueue(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError>;
+    fn dequeue(&self) -> Result<T, ThreadSafeQueueError>;
+    fn try_dequeue(&self) -> Option<T>;
+    fn try_dequeue_timeout(&self, dur: Duration) -> Result<Option<T>, ThreadSafeQueueError>;
+    fn close(&self);
+    fn is_closed(&self) -> bool;
+    fn cancel(&self);
+    fn is_cancelled(&self) -> bool;
+}
+
+/// A queue shared between threads without naming its type
+pub type SharedQueue<T> = Arc<dyn BlockingQueue<T>>;
+
+impl<T: Send + 'static> BlockingQueue<T> for ThreadSafeQueue<T> {
+    fn capacity(&self) -> Option<usize> {
+        ThreadSafeQueue::capacity(self)
+    }
+
+    fn elements(&self) -> usize {
+        ThreadSafeQueue::elements(self)
+    }
+
+    fn enqueue(&self, data: T) -> Result<(), ThreadSafeQueueError> {
+        ThreadSafeQueue::enqueue(self, data)
+    }
+
+    fn try_enqueue(&self, data: T) -> Result<(), EnqueueError<T>> {
+        ThreadSafeQueue::try_enqueue(self, data)
+    }
+
+    fn enqueue_timeout(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>> {
+        ThreadSafeQueue::enqueue_timeout(self, data, dur)
+    }
+
+    fn force_enqueue(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError> {
+        ThreadSafeQueue::force_enqueue(self, data)
+    }
+
+    fn dequeue(&self) -> Result<T, ThreadSafeQueueError> {
+        ThreadSafeQueue::dequeue(self)
+    }
+
+    fn try_dequeue(&self) -> Option<T> {
+        ThreadSafeQueue::try_dequeue(self)
+    }
+
+    fn try_dequeue_timeout(&self, dur: Duration) -> Result<Option<T>, ThreadSafeQueueError> {
+        ThreadSafeQueue::try_dequeue_timeout(self, dur)
+    }
+
+    fn close(&self) {
+        ThreadSafeQueue::close(self)
+    }
+
+    fn is_closed(&self) -> bool {
+        ThreadSafeQueue::is_closed(self)
+    }
+
+    fn cancel(&self) {
+        ThreadSafeQueue::cancel(self)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        ThreadSafeQueue::is_cancelled(self)
+    }
+}
+
+impl<T: Send> BlockingQueue<T> for RingQueue<T> {
+    fn capacity(&self) -> Option<usize> {
+        Some(RingQueue::capacity(self))
+    }
+
+    fn elements(&self) -> usize {
+        RingQueue::elements(self)
+    }
+
+    fn enqueue(&self, data: T) -> Result<(), ThreadSafeQueueError> {
+        RingQueue::enqueue(self, data)
+    }
+
+    fn try_enqueue(&self, data: T) -> Result<(), EnqueueError<T>> {
+        RingQueue::try_enqueue(self, data)
+    }
+
+    fn enqueue_timeout(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>> {
+        RingQueue::enqueue_timeout(self, data, dur)
+    }
+
+    fn force_enqueue(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError> {
+        RingQueue::force_enqueue(self, data)
+    }
+
+    fn dequeue(&self) -> Result<T, ThreadSafeQueueError> {
+        RingQueue::dequeue(self)
+    }
+
+    fn try_dequeue(&self) -> Option<T> {
+        RingQueue::try_dequeue(self)
+    }
+
+    fn try_dequeue_timeout(&self, dur: Duration) -> Result<Option<T>, ThreadSafeQueueError> {
+        RingQueue::try_dequeue_timeout(self, dur)
+    }
+
+    fn close(&self) {
+        RingQueue::close(self)
+    }
+
+    fn is_closed(&self) -> bool {
+        RingQueue::is_closed(self)
+    }
+
+    fn cancel(&self) {
+        RingQueue::cancel(self)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        RingQueue::is_cancelled(self)
+    }
+}
@@ -1,33 +1,114 @@
-use std::{
-    collections::VecDeque, sync::Arc, time::Duration
-};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use thiserror::Error;
 
-use crate::signal::{self, Signal, SignallableData};
+use crate::signal::{self, CancellationToken, Signal, SignalWaiter, SignallableData, SignallableLock};
+
+mod blocking;
+mod priority;
+mod ring;
+mod stream;
+
+pub use blocking::{BlockingQueue, SharedQueue};
+use priority::Lanes;
+pub use priority::{Prioritized, Priority};
+pub use ring::RingQueue;
+pub use stream::{Dequeue, QueueStream};
 
 #[derive(Error, Debug)]
 pub enum ThreadSafeQueueError {
     #[error("The queue mutex was poisoned.")]
     MutexPoison,
-    #[error("The status is not OK.")]
-    StatusNotOk,
+    /// The queue was closed and, when dequeuing, everything in it has been taken
+    #[error("The queue is closed.")]
+    Closed,
+    /// The queue was cancelled, any elements left in it are abandoned
+    #[error("The queue was cancelled.")]
+    Cancelled,
+    #[error("The queue is full.")]
+    Full,
+}
+
+/// An element that could not be queued, handed back along with the reason
+#[derive(Error)]
+#[error("{error}")]
+pub struct EnqueueError<T> {
+    data: T,
+    error: ThreadSafeQueueError,
+}
+
+impl<T> Debug for EnqueueError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnqueueError").field("error", &self.error).finish_non_exhaustive()
+    }
+}
+
+impl<T> EnqueueError<T> {
+    pub fn error(&self) -> &ThreadSafeQueueError {
+        &self.error
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T> From<EnqueueError<T>> for ThreadSafeQueueError {
+    fn from(value: EnqueueError<T>) -> Self {
+        value.error
+    }
+}
+
+struct QueueState<T> {
+    items: Lanes<T>,
+    closed: bool,
+}
+
+impl<T> Default for QueueState<T> {
+    fn default() -> Self {
+        Self { items: Lanes::default(), closed: false }
+    }
 }
 
+/// A FIFO queue shared between threads.
+///
+/// Elements can be given a [`Priority`], higher priorities overtaking anything of a lower priority
+/// that is still queued. Unless stated otherwise elements are queued as [`Priority::Normal`].
+///
+/// It can be shut down two ways. [`close`](Self::close) stops new elements from being queued
+/// while consumers take whatever is left, after which they get [`ThreadSafeQueueError::Closed`].
+/// [`cancel`](Self::cancel), the same as setting its [`Signal`], stops everything straight away
+/// with [`ThreadSafeQueueError::Cancelled`], waking any thread blocked on the queue.
 #[derive(Default)]
 pub struct ThreadSafeQueue<T: Send + 'static> {
-    queue: Arc<SignallableData<VecDeque<T>>>,
+    queue: Arc<SignallableData<QueueState<T>>>,
+    capacity: Option<usize>,
 }
 
 impl<T: Send + 'static> Clone for ThreadSafeQueue<T> {
     fn clone(&self) -> Self {
-        Self { queue: self.queue.clone() }
+        Self { queue: self.queue.clone(), capacity: self.capacity }
     }
 }
 
 unsafe impl<T: Send + 'static> Send for ThreadSafeQueue<T> {}
 unsafe impl<T: Send + 'static> Sync for ThreadSafeQueue<T> {}
 
+fn is_full<T>(queue: &Lanes<T>, capacity: Option<usize>) -> bool {
+    capacity.is_some_and(|capacity| queue.len() >= capacity)
+}
+
+/// Why nothing more can be queued, if the queue has been closed or cancelled
+fn stopped<T>(lock: &SignallableLock<'_, QueueState<T>>) -> Option<ThreadSafeQueueError> {
+    if lock.is_signalled() {
+        Some(ThreadSafeQueueError::Cancelled)
+    } else if lock.closed {
+        Some(ThreadSafeQueueError::Closed)
+    } else {
+        None
+    }
+}
+
 impl<T: Send + 'static> Signal for ThreadSafeQueue<T> {
     fn is_signalled(&self) -> bool {
         self.queue.is_signalled()
@@ -40,48 +121,363 @@ impl<T: Send + 'static> Signal for ThreadSafeQueue<T> {
     fn set_signal(&self, value: bool) -> bool {
         self.queue.set_signal(value)
     }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.queue.register_waiter(waiter)
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.queue.unregister_waiter(waiter)
+    }
+}
+
+/// A [`Signal`] that is set while there is something to dequeue, or once dequeuing would fail
+/// because the queue was closed or cancelled. See [`ThreadSafeQueue::ready`].
+pub struct QueueReady<'q, T: Send + 'static> {
+    queue: &'q ThreadSafeQueue<T>,
+}
+
+fn can_dequeue<T>(queue: &QueueState<T>, signal: bool) -> bool {
+    !queue.items.is_empty() || queue.closed || signal
+}
+
+impl<T: Send + 'static> Signal for QueueReady<'_, T> {
+    fn is_signalled(&self) -> bool {
+        // A poisoned queue can't be dequeued from, which counts as ready for the same reason
+        self.queue.queue.peek(can_dequeue).unwrap_or(true)
+    }
+
+    fn wait_for_signal(&self) -> Result<(), signal::SignalResult> {
+        self.queue
+            .queue
+            .lock_wait_while(|queue, signal| !can_dequeue(queue, *signal))
+            .map(|_| ())
+    }
+
+    /// Setting the signal cancels the queue
+    fn set_signal(&self, value: bool) -> bool {
+        self.queue.set_signal(value)
+    }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.queue.register_waiter(waiter)
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.queue.unregister_waiter(waiter)
+    }
 }
 
 impl<T: Send + 'static> ThreadSafeQueue<T> {
     pub fn new() -> Self {
         let queue = Arc::new(SignallableData::default());
-        Self { queue }
+        Self { queue, capacity: None }
+    }
+
+    /// Creates a queue that holds at most `capacity` elements. Producers wait in
+    /// [`enqueue`](Self::enqueue) while it is full.
+    pub fn bounded(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let queue = Arc::new(SignallableData::new(QueueState {
+            items: Lanes::with_capacity(capacity),
+            closed: false,
+        }));
+        Self { queue, capacity: Some(capacity) }
+    }
+
+    /// The most elements the queue holds, `None` if it is unbounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.peek(|queue, _| is_full(&queue.items, self.capacity)).unwrap_or_default()
+    }
+
+    /// Stops new elements from being queued. Consumers still get everything already queued.
+    pub fn close(&self) {
+        // The lock wakes every waiting thread when dropped
+        if let Ok(mut lock) = self.queue.lock() {
+            lock.closed = true;
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.peek(|queue, _| queue.closed).unwrap_or_default()
+    }
+
+    /// Stops the queue immediately, abandoning anything still in it. The same as setting its signal.
+    pub fn cancel(&self) {
+        self.queue.set_signal(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.queue.is_signalled()
+    }
+
+    /// Closes the queue once `token` is cancelled, see [`close`](Self::close)
+    pub fn close_on(&self, token: &CancellationToken) {
+        let queue = Arc::downgrade(&self.queue);
+        token.on_cancel(move || {
+            if let Some(queue) = queue.upgrade()
+                && let Ok(mut lock) = queue.lock()
+            {
+                lock.closed = true;
+            }
+        });
+    }
+
+    /// Cancels the queue once `token` is cancelled, see [`cancel`](Self::cancel)
+    pub fn cancel_on(&self, token: &CancellationToken) {
+        let queue = Arc::downgrade(&self.queue);
+        token.on_cancel(move || {
+            if let Some(queue) = queue.upgrade() {
+                queue.set_signal(true);
+            }
+        });
+    }
+
+    /// A signal for when [`dequeue`](Self::dequeue) would return without waiting, letting
+    /// [`wait_any`](signal::wait_any) watch for data alongside other signals
+    pub fn ready(&self) -> QueueReady<'_, T> {
+        QueueReady { queue: self }
     }
 
+    /// Waits for an element.
+    ///
+    /// Fails with [`ThreadSafeQueueError::Closed`] once the queue is closed and empty, or with
+    /// [`ThreadSafeQueueError::Cancelled`] as soon as it is cancelled.
     pub fn dequeue(&self) -> Result<T, ThreadSafeQueueError> {
         let mut lock = self
             .queue
-            .lock_wait_while(|queue, signal| queue.is_empty() && !signal)
+            .lock_wait_while(|queue, signal| !can_dequeue(queue, *signal))
             .map_err(|_| ThreadSafeQueueError::MutexPoison)?;
-        if !lock.is_signalled() {
-            Ok(lock.pop_front().unwrap())
-        } else {
-            Err(ThreadSafeQueueError::StatusNotOk)
+        if lock.is_signalled() {
+            return Err(ThreadSafeQueueError::Cancelled);
+        }
+        lock.items.pop_front().ok_or(ThreadSafeQueueError::Closed)
+    }
+
+    /// Like [`dequeue`](Self::dequeue), but also gives up with [`ThreadSafeQueueError::Cancelled`]
+    /// once `token` is cancelled. The queue itself is left as it is.
+    pub fn dequeue_until(&self, token: &CancellationToken) -> Result<T, ThreadSafeQueueError> {
+        let queue = Arc::downgrade(&self.queue);
+        // Dropping a lock wakes every thread waiting on the queue, this one included
+        let callback = token.on_cancel(move || {
+            if let Some(queue) = queue.upgrade() {
+                drop(queue.lock());
+            }
+        });
+        let Some(callback) = callback else {
+            return Err(ThreadSafeQueueError::Cancelled);
+        };
+        let result = self
+            .queue
+            .lock_wait_while(|queue, signal| !can_dequeue(queue, *signal) && !token.is_cancelled())
+            .map_err(|_| ThreadSafeQueueError::MutexPoison)
+            .and_then(|mut lock| {
+                if lock.is_signalled() || token.is_cancelled() {
+                    return Err(ThreadSafeQueueError::Cancelled);
+                }
+                lock.items.pop_front().ok_or(ThreadSafeQueueError::Closed)
+            });
+        token.remove_callback(callback);
+        result
+    }
+
+    /// Resolves to the next element, on any executor. Fails the same way as
+    /// [`dequeue`](Self::dequeue), which can be used on the same queue at the same time.
+    pub fn dequeue_async(&self) -> Dequeue<'_, T> {
+        Dequeue::new(self)
+    }
+
+    /// Every element dequeued as it arrives, ending once the queue is closed and empty or cancelled
+    pub fn stream(&self) -> QueueStream<T> {
+        QueueStream::new(self.clone())
+    }
+
+    /// Takes the next element, or the reason there won't be one, if that doesn't need waiting for.
+    /// Only locks the queue, waking everything waiting on it, when there is something to take.
+    fn take_ready(&self) -> Option<Result<T, ThreadSafeQueueError>> {
+        match self.queue.peek(can_dequeue) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(_) => return Some(Err(ThreadSafeQueueError::MutexPoison)),
+        }
+        let mut lock = match self.queue.lock() {
+            Ok(lock) => lock,
+            Err(_) => return Some(Err(ThreadSafeQueueError::MutexPoison)),
+        };
+        if lock.is_signalled() {
+            return Some(Err(ThreadSafeQueueError::Cancelled));
+        }
+        match lock.items.pop_front() {
+            Some(data) => Some(Ok(data)),
+            None if lock.closed => Some(Err(ThreadSafeQueueError::Closed)),
+            // Another consumer got there first
+            None => None,
         }
     }
 
     pub fn elements(&self) -> usize {
-        self.queue.lock().map(|l| l.len()).unwrap_or_default()
+        self.queue.peek(|queue, _| queue.items.len()).unwrap_or_default()
     }
 
+    /// Takes the next element if there is one. Unlike the other ways of dequeuing, this still
+    /// returns elements left behind in a cancelled queue so they can be cleaned up.
     pub fn try_dequeue(&self) -> Option<T> {
         self.queue
             .lock()
-            .map_or_else(|_| None, |mut l| l.pop_front())
+            .map_or_else(|_| None, |mut l| l.items.pop_front())
     }
 
+    /// Like [`dequeue`](Self::dequeue), but returns `None` if nothing was queued within `dur`
     pub fn try_dequeue_timeout(&self, dur: Duration) -> Result<Option<T>, ThreadSafeQueueError> {
-        self.queue
-            .lock_wait_while_timeout(dur, |queue, _| queue.is_empty())
-            .map(|opt| opt.map(|mut queue| queue.pop_front().unwrap()))
-            .map_err(|_| ThreadSafeQueueError::MutexPoison)
+        let lock = self
+            .queue
+            .lock_wait_while_timeout(dur, |queue, signal| !can_dequeue(queue, *signal))
+            .map_err(|_| ThreadSafeQueueError::MutexPoison)?;
+        match lock {
+            Some(lock) if lock.is_signalled() => Err(ThreadSafeQueueError::Cancelled),
+            Some(mut lock) => lock.items.pop_front().map(Some).ok_or(ThreadSafeQueueError::Closed),
+            None => Ok(None),
+        }
     }
 
+    /// Queues `data`, waiting for room if the queue is bounded and full.
+    ///
+    /// Fails if the queue is closed or cancelled, including while waiting, so shutting it down
+    /// wakes every blocked producer.
     pub fn enqueue(&self, data: T) -> Result<(), ThreadSafeQueueError> {
-        let mut lock = self.queue.lock().map_err(|_| ThreadSafeQueueError::MutexPoison)?;
-        lock.push_back(data);
+        self.enqueue_with_priority(data, Priority::Normal)
+    }
+
+    /// Queues `data` with the priority it declares, see [`enqueue`](Self::enqueue)
+    pub fn enqueue_prioritized(&self, data: T) -> Result<(), ThreadSafeQueueError>
+    where
+        T: Prioritized,
+    {
+        let priority = data.priority();
+        self.enqueue_with_priority(data, priority)
+    }
+
+    /// Queues `data` in the lane for `priority`, see [`enqueue`](Self::enqueue)
+    pub fn enqueue_with_priority(&self, data: T, priority: Priority) -> Result<(), ThreadSafeQueueError> {
+        let capacity = self.capacity;
+        let mut lock = self
+            .queue
+            .lock_wait_while(|queue, signal| is_full(&queue.items, capacity) && !queue.closed && !signal)
+            .map_err(|_| ThreadSafeQueueError::MutexPoison)?;
+        if let Some(error) = stopped(&lock) {
+            return Err(error);
+        }
+        lock.items.push_back(data, priority);
         Ok(())
     }
+
+    /// Queues `data` only if there is room for it right now
+    pub fn try_enqueue(&self, data: T) -> Result<(), EnqueueError<T>> {
+        self.try_enqueue_with_priority(data, Priority::Normal)
+    }
+
+    /// Like [`try_enqueue`](Self::try_enqueue), with the priority `data` declares
+    pub fn try_enqueue_prioritized(&self, data: T) -> Result<(), EnqueueError<T>>
+    where
+        T: Prioritized,
+    {
+        let priority = data.priority();
+        self.try_enqueue_with_priority(data, priority)
+    }
+
+    /// Like [`try_enqueue`](Self::try_enqueue), in the lane for `priority`
+    pub fn try_enqueue_with_priority(&self, data: T, priority: Priority) -> Result<(), EnqueueError<T>> {
+        let mut lock = match self.queue.lock() {
+            Ok(lock) => lock,
+            Err(_) => return Err(EnqueueError { data, error: ThreadSafeQueueError::MutexPoison }),
+        };
+        if let Some(error) = stopped(&lock) {
+            return Err(EnqueueError { data, error });
+        }
+        if is_full(&lock.items, self.capacity) {
+            return Err(EnqueueError { data, error: ThreadSafeQueueError::Full });
+        }
+        lock.items.push_back(data, priority);
+        Ok(())
+    }
+
+    /// Like [`enqueue`](Self::enqueue), but gives up with [`ThreadSafeQueueError::Full`] if there
+    /// is still no room after `dur`
+    pub fn enqueue_timeout(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>> {
+        self.enqueue_timeout_with_priority(data, dur, Priority::Normal)
+    }
+
+    /// Like [`enqueue_timeout`](Self::enqueue_timeout), with the priority `data` declares
+    pub fn enqueue_timeout_prioritized(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>>
+    where
+        T: Prioritized,
+    {
+        let priority = data.priority();
+        self.enqueue_timeout_with_priority(data, dur, priority)
+    }
+
+    /// Like [`enqueue_timeout`](Self::enqueue_timeout), in the lane for `priority`
+    pub fn enqueue_timeout_with_priority(
+        &self,
+        data: T,
+        dur: Duration,
+        priority: Priority,
+    ) -> Result<(), EnqueueError<T>> {
+        let capacity = self.capacity;
+        let lock = self.queue.lock_wait_while_timeout(dur, |queue, signal| {
+            is_full(&queue.items, capacity) && !queue.closed && !signal
+        });
+        let error = match lock {
+            Ok(Some(mut lock)) => match stopped(&lock) {
+                Some(error) => error,
+                None => {
+                    lock.items.push_back(data, priority);
+                    return Ok(());
+                }
+            },
+            Ok(None) => ThreadSafeQueueError::Full,
+            Err(_) => ThreadSafeQueueError::MutexPoison,
+        };
+        Err(EnqueueError { data, error })
+    }
+
+    /// Queues `data` without waiting, removing the oldest element of the lowest priority to make
+    /// room if the queue is full. Returns the removed element.
+    pub fn force_enqueue(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError> {
+        self.force_enqueue_with_priority(data, Priority::Normal)
+    }
+
+    /// Like [`force_enqueue`](Self::force_enqueue), with the priority `data` declares
+    pub fn force_enqueue_prioritized(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError>
+    where
+        T: Prioritized,
+    {
+        let priority = data.priority();
+        self.force_enqueue_with_priority(data, priority)
+    }
+
+    /// Like [`force_enqueue`](Self::force_enqueue), in the lane for `priority`. If everything
+    /// queued is more important than `data`, `data` itself is handed back instead.
+    pub fn force_enqueue_with_priority(&self, data: T, priority: Priority) -> Result<Option<T>, ThreadSafeQueueError> {
+        let mut lock = self.queue.lock().map_err(|_| ThreadSafeQueueError::MutexPoison)?;
+        if let Some(error) = stopped(&lock) {
+            return Err(error);
+        }
+        let evicted = if is_full(&lock.items, self.capacity) {
+            if lock.items.lowest_priority().is_some_and(|lowest| lowest > priority) {
+                return Ok(Some(data));
+            }
+            lock.items.pop_least_important()
+        } else {
+            None
+        };
+        lock.items.push_back(data, priority);
+        Ok(evicted)
+    }
 }
 
 #[cfg(test)]
@@ -103,4 +499,105 @@ mod tests {
         assert!(queue.dequeue().is_ok_and(|s| s.eq(str3)));
         drop(queue);
     }
+
+    #[test]
+    fn bounded_enqueue_waits_for_room() {
+        let queue = ThreadSafeQueue::<u32>::bounded(2);
+        assert!(queue.enqueue(1).is_ok());
+        assert!(queue.try_enqueue(2).is_ok());
+        let full = queue.try_enqueue(3).unwrap_err();
+        assert!(matches!(full.error(), ThreadSafeQueueError::Full));
+        assert_eq!(full.into_inner(), 3);
+        assert!(queue.enqueue_timeout(3, Duration::from_millis(10)).is_err());
+        assert!(matches!(queue.force_enqueue(3), Ok(Some(1))));
+
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.enqueue(4))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(queue.dequeue().is_ok_and(|n| n == 2));
+        assert!(producer.join().unwrap().is_ok());
+        assert_eq!(queue.elements(), 2);
+
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.enqueue(5))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        queue.set_signal(true);
+        assert!(matches!(producer.join().unwrap(), Err(ThreadSafeQueueError::Cancelled)));
+    }
+
+    #[test]
+    fn close_drains_and_cancel_abandons() {
+        let queue = ThreadSafeQueue::<u32>::new();
+        let consumer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                let mut received = Vec::new();
+                loop {
+                    match queue.dequeue() {
+                        Ok(n) => received.push(n),
+                        Err(e) => return (received, e),
+                    }
+                }
+            })
+        };
+        for n in 0..3 {
+            assert!(queue.enqueue(n).is_ok());
+        }
+        queue.close();
+        assert!(matches!(queue.enqueue(3), Err(ThreadSafeQueueError::Closed)));
+        let (received, error) = consumer.join().unwrap();
+        assert_eq!(received, [0, 1, 2]);
+        assert!(matches!(error, ThreadSafeQueueError::Closed));
+
+        let queue = ThreadSafeQueue::<u32>::new();
+        assert!(queue.enqueue(0).is_ok());
+        queue.cancel();
+        assert!(matches!(queue.dequeue(), Err(ThreadSafeQueueError::Cancelled)));
+        assert!(matches!(queue.try_enqueue(1).map_err(|e| e.into()), Err(ThreadSafeQueueError::Cancelled)));
+        assert_eq!(queue.try_dequeue(), Some(0));
+    }
+
+    #[test]
+    fn priorities_overtake_in_order() {
+        let queue = ThreadSafeQueue::<&str>::bounded(5);
+        assert!(queue.enqueue_with_priority("low", Priority::Low).is_ok());
+        assert!(queue.enqueue("first").is_ok());
+        assert!(queue.enqueue("second").is_ok());
+        assert!(queue.enqueue_with_priority("urgent", Priority::High).is_ok());
+        assert!(queue.enqueue_with_priority("also urgent", Priority::High).is_ok());
+        assert!(matches!(queue.force_enqueue("third"), Ok(Some("low"))));
+        let order = std::iter::from_fn(|| queue.try_dequeue()).collect::<Vec<_>>();
+        assert_eq!(order, ["urgent", "also urgent", "first", "second", "third"]);
+
+        let queue = ThreadSafeQueue::<Message>::bounded(2);
+        assert!(queue.try_enqueue_prioritized(Message::Data).is_ok());
+        assert!(queue.enqueue_timeout_prioritized(Message::Ack, Duration::from_millis(10)).is_ok());
+        let full = queue.try_enqueue_prioritized(Message::Ack).unwrap_err();
+        assert!(matches!(full.error(), ThreadSafeQueueError::Full));
+        assert!(matches!(queue.force_enqueue_prioritized(Message::Trace), Ok(Some(Message::Trace))));
+        assert!(matches!(queue.force_enqueue_prioritized(Message::Ack), Ok(Some(Message::Data))));
+        let order = std::iter::from_fn(|| queue.try_dequeue()).collect::<Vec<_>>();
+        assert_eq!(order, [Message::Ack, Message::Ack]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Message {
+        Trace,
+        Data,
+        Ack,
+    }
+
+    impl Prioritized for Message {
+        fn priority(&self) -> Priority {
+            match self {
+                Message::Trace => Priority::Low,
+                Message::Data => Priority::Normal,
+                Message::Ack => Priority::High,
+            }
+        }
+    }
 }
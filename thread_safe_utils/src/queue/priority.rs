@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+/// The lane an element is queued in. Higher priorities are dequeued first, elements of the same
+/// priority in the order they were queued.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    const COUNT: usize = 3;
+
+    fn lane(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Implemented by element types that know how urgent they are, see
+/// [`ThreadSafeQueue::enqueue_prioritized`](super::ThreadSafeQueue::enqueue_prioritized)
+pub trait Prioritized {
+    fn priority(&self) -> Priority;
+}
+
+/// One FIFO per [`Priority`]
+pub(crate) struct Lanes<T> {
+    lanes: [VecDeque<T>; Priority::COUNT],
+}
+
+impl<T> Default for Lanes<T> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl<T> Lanes<T> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            // Most elements are normal priority
+            lanes: [VecDeque::new(), VecDeque::with_capacity(capacity), VecDeque::new()],
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lanes.iter().map(VecDeque::len).sum()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lanes.iter().all(VecDeque::is_empty)
+    }
+
+    pub(crate) fn push_back(&mut self, data: T, priority: Priority) {
+        self.lanes[priority.lane()].push_back(data);
+    }
+
+    /// Takes the oldest element of the highest priority
+    pub(crate) fn pop_front(&mut self) -> Option<T> {
+        self.lanes.iter_mut().rev().find_map(VecDeque::pop_front)
+    }
+
+    /// The priority of the least important element queued
+    pub(crate) fn lowest_priority(&self) -> Option<Priority> {
+        [Priority::Low, Priority::Normal, Priority::High]
+            .into_iter()
+            .find(|priority| !self.lanes[priority.lane()].is_empty())
+    }
+
+    /// Takes the oldest element of the lowest priority, the one that matters least
+    pub(crate) fn pop_least_important(&mut self) -> Option<T> {
+        self.lanes.iter_mut().find_map(VecDeque::pop_front)
+    }
+}
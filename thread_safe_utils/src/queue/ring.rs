@@ -0,0 +1,551 @@
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::{
+        Arc, Condvar, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering, fence},
+    },
+    time::{Duration, Instant},
+};
+
+use super::{EnqueueError, ThreadSafeQueueError};
+use crate::signal::{Signal, SignalResult, SignalWaiter, WaiterList};
+
+/// How many times a waiting thread checks the queue again before parking
+const SPINS: u32 = 64;
+
+/// Keeps the producer and consumer positions on separate cache lines
+#[repr(align(64))]
+#[derive(Default)]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+struct Slot<T> {
+    /// Equal to the slot's position once it can be written to, and one past it once it holds a
+    /// value ready to be read
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Ring<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+    closed: AtomicBool,
+    cancelled: AtomicBool,
+    /// Producers that found the queue open and may not have published their element yet
+    publishing: AtomicUsize,
+    /// Threads parked waiting for room or for an element. Only while there are any does anything
+    /// touch `lock`.
+    sleepers: AtomicUsize,
+    lock: Mutex<()>,
+    condvar: Condvar,
+    waiters: WaiterList,
+}
+
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        let slots = (0..capacity)
+            .map(|i| Slot {
+                sequence: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            slots,
+            mask: capacity - 1,
+            enqueue_pos: Default::default(),
+            dequeue_pos: Default::default(),
+            closed: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            publishing: AtomicUsize::new(0),
+            sleepers: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            condvar: Condvar::new(),
+            waiters: WaiterList::default(),
+        }
+    }
+
+    fn push(&self, data: T) -> Result<(), T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos) as isize {
+                0 => match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // Winning the exchange gives this thread sole access to the slot
+                        unsafe { (*slot.value.get()).write(data) };
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        self.notify();
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                },
+                // The slot still holds the value from a lap ago, the ring is full
+                difference if difference < 0 => return Err(data),
+                _ => pos = self.enqueue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Like [`push`](Self::push), but if the ring is full takes the oldest element's slot instead,
+    /// returning that element. Never waits for a consumer to make room.
+    fn push_evicting(&self, data: T) -> Option<T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos) as isize {
+                0 => match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(data) };
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        self.notify();
+                        return None;
+                    }
+                    Err(current) => pos = current,
+                },
+                difference if difference < 0 => {
+                    // The slot holds the oldest element once it is ready to be read. Dequeuing it
+                    // as a consumer would, without freeing the slot, leaves this thread the only
+                    // one able to write there: producers see it as full and other evictions need
+                    // the old dequeue position.
+                    let oldest = pos.wrapping_sub(self.mask + 1);
+                    if sequence == oldest.wrapping_add(1)
+                        && self
+                            .dequeue_pos
+                            .compare_exchange(oldest, oldest.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed)
+                            .is_ok()
+                    {
+                        let evicted = unsafe { (*slot.value.get()).assume_init_read() };
+                        // Nothing else can move the enqueue position off `pos` until the slot is
+                        // published
+                        self.enqueue_pos.store(pos.wrapping_add(1), Ordering::Relaxed);
+                        unsafe { (*slot.value.get()).write(data) };
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        self.notify();
+                        return Some(evicted);
+                    }
+                    // Another thread is part way through taking or filling the slot
+                    std::hint::spin_loop();
+                    pos = self.enqueue_pos.load(Ordering::Relaxed);
+                }
+                _ => pos = self.enqueue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos.wrapping_add(1)) as isize {
+                0 => match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let data = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.sequence.store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        self.notify();
+                        return Some(data);
+                    }
+                    Err(current) => pos = current,
+                },
+                difference if difference < 0 => return None,
+                _ => pos = self.dequeue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    fn can_push(&self) -> bool {
+        let pos = self.enqueue_pos.load(Ordering::Relaxed);
+        self.slots[pos & self.mask].sequence.load(Ordering::Acquire) == pos
+    }
+
+    fn can_pop(&self) -> bool {
+        let pos = self.dequeue_pos.load(Ordering::Relaxed);
+        self.slots[pos & self.mask].sequence.load(Ordering::Acquire) == pos.wrapping_add(1)
+    }
+
+    fn len(&self) -> usize {
+        let dequeued = self.dequeue_pos.load(Ordering::Acquire);
+        let enqueued = self.enqueue_pos.load(Ordering::Acquire);
+        enqueued.wrapping_sub(dequeued).min(self.mask + 1)
+    }
+
+    fn stopped(&self) -> Option<ThreadSafeQueueError> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Some(ThreadSafeQueueError::Cancelled)
+        } else if self.closed.load(Ordering::SeqCst) {
+            Some(ThreadSafeQueueError::Closed)
+        } else {
+            None
+        }
+    }
+
+    /// Fails if the queue is stopped. Otherwise counts this thread in `publishing` until the
+    /// returned guard is dropped, so consumers that find the queue closed wait for its element.
+    fn start_publishing(&self) -> Result<Publishing<'_>, ThreadSafeQueueError> {
+        // Sequentially consistent with `close`, so either this sees the queue closed or the
+        // consumer sees this thread publishing
+        self.publishing.fetch_add(1, Ordering::SeqCst);
+        let publishing = Publishing(&self.publishing);
+        match self.stopped() {
+            Some(error) => Err(error),
+            None => Ok(publishing),
+        }
+    }
+
+    /// Waits out producers that found the queue open before it was closed
+    fn wait_for_publishers(&self) {
+        while self.publishing.load(Ordering::SeqCst) > 0 {
+            std::thread::yield_now();
+        }
+    }
+
+    /// Wakes parked threads after the queue changed
+    fn notify(&self) {
+        // Pairs with the fence in `wait_until`, so either the sleeper sees the change or this
+        // sees the sleeper
+        fence(Ordering::SeqCst);
+        if self.sleepers.load(Ordering::Relaxed) > 0 {
+            let _lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Waits until `ready` or the deadline passes, returning whether `ready`
+    fn wait_until(&self, deadline: Option<Instant>, ready: impl Fn() -> bool) -> bool {
+        for spin in 0..SPINS {
+            if ready() {
+                return true;
+            }
+            if spin < SPINS / 2 {
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        let mut lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.sleepers.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        let ready = loop {
+            if ready() {
+                break true;
+            }
+            lock = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break false;
+                    }
+                    self.condvar
+                        .wait_timeout(lock, left)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self.condvar.wait(lock).unwrap_or_else(PoisonError::into_inner),
+            };
+        };
+        self.sleepers.fetch_sub(1, Ordering::Relaxed);
+        ready
+    }
+
+    fn stop(&self, flag: &AtomicBool, value: bool) -> bool {
+        let old = flag.swap(value, Ordering::SeqCst);
+        self.notify();
+        self.waiters.wake_all();
+        old
+    }
+}
+
+struct Publishing<'a>(&'a AtomicUsize);
+
+impl Drop for Publishing<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// A bounded, lock-free alternative to [`ThreadSafeQueue`](super::ThreadSafeQueue) for any
+/// number of producers and consumers.
+///
+/// Enqueuing and dequeuing only touch atomics. Threads that have to wait for room or for an
+/// element spin briefly, then park until woken. Elements are dequeued in the order they were
+/// queued, there are no priorities. Closing and cancelling work as they do for
+/// [`ThreadSafeQueue`](super::ThreadSafeQueue), which [`BlockingQueue`](super::BlockingQueue)
+/// lets code be written against either.
+pub struct RingQueue<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T> Clone for RingQueue<T> {
+    fn clone(&self) -> Self {
+        Self { ring: self.ring.clone() }
+    }
+}
+
+impl<T: Send> RingQueue<T> {
+    /// Creates a queue holding at least `capacity` elements, rounded up to a power of two
+    pub fn bounded(capacity: usize) -> Self {
+        Self { ring: Arc::new(Ring::new(capacity)) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.mask + 1
+    }
+
+    pub fn elements(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        !self.ring.can_push()
+    }
+
+    /// Stops new elements from being queued. Consumers still get everything already queued.
+    pub fn close(&self) {
+        self.ring.stop(&self.ring.closed, true);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring.closed.load(Ordering::Acquire)
+    }
+
+    /// Stops the queue immediately, abandoning anything still in it. The same as setting its signal.
+    pub fn cancel(&self) {
+        self.ring.stop(&self.ring.cancelled, true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.ring.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Waits for an element.
+    ///
+    /// Fails with [`ThreadSafeQueueError::Closed`] once the queue is closed and empty, or with
+    /// [`ThreadSafeQueueError::Cancelled`] as soon as it is cancelled.
+    pub fn dequeue(&self) -> Result<T, ThreadSafeQueueError> {
+        self.dequeue_before(None)?.ok_or(ThreadSafeQueueError::Closed)
+    }
+
+    /// Takes the next element if there is one, even from a cancelled queue
+    pub fn try_dequeue(&self) -> Option<T> {
+        self.ring.pop()
+    }
+
+    /// Like [`dequeue`](Self::dequeue), but returns `None` if nothing was queued within `dur`
+    pub fn try_dequeue_timeout(&self, dur: Duration) -> Result<Option<T>, ThreadSafeQueueError> {
+        self.dequeue_before(Some(Instant::now() + dur))
+    }
+
+    fn dequeue_before(&self, deadline: Option<Instant>) -> Result<Option<T>, ThreadSafeQueueError> {
+        let ring = &self.ring;
+        loop {
+            if ring.cancelled.load(Ordering::Acquire) {
+                return Err(ThreadSafeQueueError::Cancelled);
+            }
+            if let Some(data) = ring.pop() {
+                return Ok(Some(data));
+            }
+            if ring.closed.load(Ordering::SeqCst) {
+                // Every enqueue that succeeded is visible once the ones in progress finish
+                ring.wait_for_publishers();
+                return ring.pop().map(Some).ok_or(ThreadSafeQueueError::Closed);
+            }
+            let ready = || ring.can_pop() || ring.stopped().is_some();
+            if !ring.wait_until(deadline, ready) {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Queues `data`, waiting for room while the queue is full. Fails if the queue is closed or
+    /// cancelled, including while waiting.
+    pub fn enqueue(&self, data: T) -> Result<(), ThreadSafeQueueError> {
+        self.enqueue_before(data, None).map_err(|e| e.error)
+    }
+
+    /// Queues `data` only if there is room for it right now
+    pub fn try_enqueue(&self, data: T) -> Result<(), EnqueueError<T>> {
+        let _publishing = match self.ring.start_publishing() {
+            Ok(publishing) => publishing,
+            Err(error) => return Err(EnqueueError { data, error }),
+        };
+        self.ring.push(data).map_err(|data| EnqueueError { data, error: ThreadSafeQueueError::Full })
+    }
+
+    /// Like [`enqueue`](Self::enqueue), but gives up with [`ThreadSafeQueueError::Full`] if there
+    /// is still no room after `dur`
+    pub fn enqueue_timeout(&self, data: T, dur: Duration) -> Result<(), EnqueueError<T>> {
+        self.enqueue_before(data, Some(Instant::now() + dur))
+    }
+
+    fn enqueue_before(&self, mut data: T, deadline: Option<Instant>) -> Result<(), EnqueueError<T>> {
+        let ring = &self.ring;
+        loop {
+            {
+                let _publishing = match ring.start_publishing() {
+                    Ok(publishing) => publishing,
+                    Err(error) => return Err(EnqueueError { data, error }),
+                };
+                match ring.push(data) {
+                    Ok(()) => return Ok(()),
+                    Err(rejected) => data = rejected,
+                }
+            }
+            if !ring.wait_until(deadline, || ring.can_push() || ring.stopped().is_some()) {
+                return Err(EnqueueError { data, error: ThreadSafeQueueError::Full });
+            }
+        }
+    }
+
+    /// Queues `data` without waiting, removing the oldest element to make room if the queue is
+    /// full. Returns the removed element.
+    pub fn force_enqueue(&self, data: T) -> Result<Option<T>, ThreadSafeQueueError> {
+        let _publishing = self.ring.start_publishing()?;
+        Ok(self.ring.push_evicting(data))
+    }
+}
+
+impl<T: Send> Signal for RingQueue<T> {
+    fn is_signalled(&self) -> bool {
+        self.is_cancelled()
+    }
+
+    fn wait_for_signal(&self) -> Result<(), SignalResult> {
+        self.ring.wait_until(None, || self.is_cancelled());
+        Ok(())
+    }
+
+    fn set_signal(&self, value: bool) -> bool {
+        self.ring.stop(&self.ring.cancelled, value)
+    }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.ring.waiters.register(waiter);
+        true
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.ring.waiters.unregister(waiter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mpsc_keeps_per_producer_order() {
+        let queue = RingQueue::<(usize, usize)>::bounded(6);
+        assert_eq!(queue.capacity(), 8);
+        let producers = (0..4)
+            .map(|producer| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    for n in 0..1000 {
+                        assert!(queue.enqueue((producer, n)).is_ok());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut next = [0; 4];
+        for _ in 0..4000 {
+            let (producer, n) = queue.dequeue().unwrap();
+            assert_eq!(next[producer], n);
+            next[producer] += 1;
+        }
+        producers.into_iter().for_each(|producer| producer.join().unwrap());
+
+        for n in 0..8 {
+            assert!(queue.try_enqueue((0, n)).is_ok());
+        }
+        assert!(matches!(queue.try_enqueue((0, 8)).map_err(|e| e.into()), Err(ThreadSafeQueueError::Full)));
+        assert!(matches!(queue.force_enqueue((0, 8)), Ok(Some((0, 0)))));
+        assert!(queue.enqueue_timeout((0, 9), Duration::from_millis(10)).is_err());
+        queue.close();
+        assert!(matches!(queue.enqueue((0, 9)), Err(ThreadSafeQueueError::Closed)));
+        let drained = std::iter::from_fn(|| queue.dequeue().ok()).map(|(_, n)| n).collect::<Vec<_>>();
+        assert_eq!(drained, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(matches!(queue.dequeue(), Err(ThreadSafeQueueError::Closed)));
+
+        let queue = RingQueue::<u32>::bounded(2);
+        let consumer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.dequeue())
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        queue.cancel();
+        assert!(matches!(consumer.join().unwrap(), Err(ThreadSafeQueueError::Cancelled)));
+    }
+
+    #[test]
+    fn force_enqueue_evicts_without_waiting() {
+        let queue = RingQueue::<usize>::bounded(4);
+        let producers = (0..4)
+            .map(|producer| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    (0..1000)
+                        .filter(|n| queue.force_enqueue(producer * 1000 + n).unwrap().is_some())
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        let evicted = producers.into_iter().map(|producer| producer.join().unwrap()).sum::<usize>();
+        // Nothing is lost: every element was either evicted or is still queued
+        assert_eq!(evicted + queue.elements(), 4000);
+        assert_eq!(queue.elements(), 4);
+    }
+
+    #[test]
+    fn accepted_elements_are_dequeued_after_close() {
+        for _ in 0..50 {
+            let queue = RingQueue::<u32>::bounded(1024);
+            let producers = (0..4)
+                .map(|_| {
+                    let queue = queue.clone();
+                    std::thread::spawn(move || (0..200).filter(|&n| queue.try_enqueue(n).is_ok()).count())
+                })
+                .collect::<Vec<_>>();
+            queue.close();
+            let drained = std::iter::from_fn(|| queue.dequeue().ok()).count();
+            let accepted = producers.into_iter().map(|producer| producer.join().unwrap()).sum::<usize>();
+            assert_eq!(drained, accepted);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    future::{Future, poll_fn},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::{ThreadSafeQueue, ThreadSafeQueueError};
+use crate::signal::AsyncWaiter;
+
+/// Resolves to the next element of a queue, see [`ThreadSafeQueue::dequeue_async`]
+pub struct Dequeue<'q, T: Send + 'static> {
+    queue: &'q ThreadSafeQueue<T>,
+    waiter: AsyncWaiter,
+}
+
+impl<'q, T: Send + 'static> Dequeue<'q, T> {
+    pub(super) fn new(queue: &'q ThreadSafeQueue<T>) -> Self {
+        Self {
+            queue,
+            waiter: AsyncWaiter::default(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Future for Dequeue<'_, T> {
+    type Output = Result<T, ThreadSafeQueueError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.waiter.arm(this.queue, cx.waker());
+        match this.queue.take_ready() {
+            Some(result) => {
+                this.waiter.disarm(this.queue);
+                Poll::Ready(result)
+            }
+            None => this.waiter.pending(cx.waker()),
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for Dequeue<'_, T> {
+    fn drop(&mut self) {
+        self.waiter.disarm(self.queue);
+    }
+}
+
+/// The elements of a queue as an async stream, see [`ThreadSafeQueue::stream`].
+///
+/// Implements `futures_core::Stream` with the `stream` feature.
+pub struct QueueStream<T: Send + 'static> {
+    queue: ThreadSafeQueue<T>,
+    waiter: AsyncWaiter,
+    finished: bool,
+}
+
+impl<T: Send + 'static> QueueStream<T> {
+    pub(super) fn new(queue: ThreadSafeQueue<T>) -> Self {
+        Self {
+            queue,
+            waiter: AsyncWaiter::default(),
+            finished: false,
+        }
+    }
+
+    /// Resolves to the next element, or `None` once the stream has ended
+    pub async fn next(&mut self) -> Option<T> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        this.waiter.arm(&this.queue, cx.waker());
+        match this.queue.take_ready() {
+            Some(Ok(data)) => Poll::Ready(Some(data)),
+            Some(Err(_)) => {
+                this.finished = true;
+                this.waiter.disarm(&this.queue);
+                Poll::Ready(None)
+            }
+            None => this.waiter.pending(cx.waker()),
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T: Send + 'static> futures_core::Stream for QueueStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        QueueStream::poll_next(self, cx)
+    }
+}
+
+impl<T: Send + 'static> Drop for QueueStream<T> {
+    fn drop(&mut self) {
+        self.waiter.disarm(&self.queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        task::{Wake, Waker},
+        thread::Thread,
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::signal::{IdleSignal, Signal};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Runs `future` on this thread, parking it until the future's waker is called
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            std::thread::park();
+        }
+    }
+
+    #[test]
+    fn async_dequeue_and_signal_wake_the_task() {
+        let queue = ThreadSafeQueue::<u32>::new();
+        let done = Arc::new(IdleSignal::new());
+        let producer = {
+            let (queue, done) = (queue.clone(), done.clone());
+            std::thread::spawn(move || {
+                for n in 0..3 {
+                    std::thread::sleep(Duration::from_millis(5));
+                    assert!(queue.enqueue(n).is_ok());
+                }
+                queue.close();
+                std::thread::sleep(Duration::from_millis(5));
+                done.set_signal(true);
+            })
+        };
+        let received = block_on(async {
+            let first = queue.dequeue_async().await;
+            let mut stream = queue.stream();
+            let mut rest = Vec::new();
+            while let Some(n) = stream.next().await {
+                rest.push(n);
+            }
+            (first.ok(), rest)
+        });
+        assert_eq!(received, (Some(0), vec![1, 2]));
+        assert!(matches!(block_on(queue.dequeue_async()), Err(ThreadSafeQueueError::Closed)));
+        block_on(done.wait_for_signal_async());
+        producer.join().unwrap();
+    }
+}
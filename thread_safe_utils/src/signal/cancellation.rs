@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use super::{IdleSignal, Signal, SignalResult, SignalWaiter};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Identifies a callback registered with [`CancellationToken::on_cancel`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallbackId(usize);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: bool,
+    children: Vec<Weak<TokenInner>>,
+    callbacks: Vec<(CallbackId, Callback)>,
+    next_callback: usize,
+}
+
+#[derive(Default)]
+struct TokenInner {
+    state: Mutex<TokenState>,
+    signal: IdleSignal,
+}
+
+/// Stops a tree of work at once.
+///
+/// Cancelling a token cancels every [`child`](Self::child) descended from it, but never its
+/// parent. Clones share the same token. As a [`Signal`] it is set once cancelled, so it can be
+/// waited on alongside other signals, and setting it cancels the token.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that is cancelled along with this one, but can be cancelled on its own
+    /// without affecting it. The child is forgotten once every clone of it has been dropped.
+    pub fn child(&self) -> Self {
+        let child = Self::new();
+        let mut state = self.state();
+        if state.cancelled {
+            drop(state);
+            child.cancel();
+        } else {
+            state.children.retain(|child| child.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.inner));
+        }
+        child
+    }
+
+    /// Cancels this token and all of its descendants, running their callbacks on this thread.
+    /// Does nothing if it is already cancelled.
+    pub fn cancel(&self) {
+        let (callbacks, children) = {
+            let mut state = self.state();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            (std::mem::take(&mut state.callbacks), std::mem::take(&mut state.children))
+        };
+        self.inner.signal.set_signal(true);
+        for (_, callback) in callbacks {
+            callback();
+        }
+        for inner in children.iter().filter_map(Weak::upgrade) {
+            CancellationToken { inner }.cancel();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.signal.is_signalled()
+    }
+
+    /// Runs `callback` when the token is cancelled, on the cancelling thread.
+    ///
+    /// If the token is already cancelled, `callback` runs straight away and `None` is returned.
+    pub fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) -> Option<CallbackId> {
+        let mut state = self.state();
+        if state.cancelled {
+            drop(state);
+            callback();
+            return None;
+        }
+        let id = CallbackId(state.next_callback);
+        state.next_callback += 1;
+        state.callbacks.push((id, Box::new(callback)));
+        Some(id)
+    }
+
+    /// Removes a callback that hasn't run yet. Returns false if it already ran or was removed.
+    pub fn remove_callback(&self, id: CallbackId) -> bool {
+        let mut state = self.state();
+        let Some(index) = state.callbacks.iter().position(|(callback, _)| *callback == id) else {
+            return false;
+        };
+        drop(state.callbacks.remove(index));
+        true
+    }
+
+    fn state(&self) -> MutexGuard<'_, TokenState> {
+        // Callbacks run without the lock held, so a poisoned state is still consistent
+        self.inner.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Signal for CancellationToken {
+    fn is_signalled(&self) -> bool {
+        self.is_cancelled()
+    }
+
+    fn wait_for_signal(&self) -> Result<(), SignalResult> {
+        self.inner.signal.wait_for_signal()
+    }
+
+    /// Setting the signal cancels the token. A cancelled token can't be reset, so clearing it
+    /// does nothing.
+    fn set_signal(&self, value: bool) -> bool {
+        let old = self.is_cancelled();
+        if value {
+            self.cancel();
+        }
+        old
+    }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.inner.signal.register_waiter(waiter)
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.inner.signal.unregister_waiter(waiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::queue::{ThreadSafeQueue, ThreadSafeQueueError};
+
+    #[test]
+    fn cancelling_reaches_descendants_and_queues() {
+        let root = CancellationToken::new();
+        let ipc = root.child();
+        let walk = ipc.child();
+        let sibling = root.child();
+        let ran = Arc::new(AtomicUsize::new(0));
+        let counter = ran.clone();
+        let callback = walk.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(callback.is_some());
+        let removed = walk.on_cancel(|| panic!("removed callbacks never run")).unwrap();
+        assert!(walk.remove_callback(removed));
+
+        let queue = ThreadSafeQueue::<u32>::new();
+        queue.cancel_on(&ipc);
+        let logs = ThreadSafeQueue::<u32>::new();
+        assert!(logs.enqueue(1).is_ok());
+        logs.close_on(&sibling);
+        assert!(logs.dequeue_until(&root).is_ok_and(|n| n == 1));
+        let waiting = {
+            let (logs, root) = (logs.clone(), root.clone());
+            std::thread::spawn(move || logs.dequeue_until(&root))
+        };
+
+        ipc.cancel();
+        assert!(ipc.is_cancelled() && walk.is_cancelled());
+        assert!(!root.is_cancelled() && !sibling.is_cancelled());
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+        assert!(queue.is_cancelled());
+        assert!(!logs.is_closed());
+
+        std::thread::sleep(Duration::from_millis(20));
+        root.cancel();
+        assert!(matches!(waiting.join().unwrap(), Err(ThreadSafeQueueError::Cancelled)));
+        assert!(logs.is_closed() && !logs.is_cancelled());
+        assert!(root.child().is_cancelled());
+        assert!(walk.on_cancel(|| ()).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use super::{Signal, SignalWaiter};
+
+/// Keeps a future's [`Waker`] registered with the signal it is waiting on
+#[derive(Default)]
+pub(crate) struct AsyncWaiter {
+    waiter: Option<Arc<SignalWaiter>>,
+    /// The signal couldn't register the waiter, so nothing will wake the future
+    polled: bool,
+}
+
+impl AsyncWaiter {
+    /// Has `waker` woken whenever `signal` may have changed. Called before checking the signal so
+    /// a change in between can't be missed.
+    pub(crate) fn arm<S: Signal + ?Sized>(&mut self, signal: &S, waker: &Waker) {
+        let waiter = match &self.waiter {
+            Some(waiter) => waiter,
+            None => {
+                let waiter = Arc::new(SignalWaiter::default());
+                self.polled = !signal.register_waiter(&waiter);
+                self.waiter.insert(waiter)
+            }
+        };
+        waiter.set_waker(waker);
+    }
+
+    pub(crate) fn pending<R>(&self, waker: &Waker) -> Poll<R> {
+        if self.polled {
+            // Try again as soon as the executor gets round to it
+            waker.wake_by_ref();
+        }
+        Poll::Pending
+    }
+
+    pub(crate) fn disarm<S: Signal + ?Sized>(&mut self, signal: &S) {
+        if let Some(waiter) = self.waiter.take() {
+            signal.unregister_waiter(&waiter);
+        }
+    }
+}
+
+/// Resolves once a signal is set, see [`Signal::wait_for_signal_async`]
+pub struct SignalFuture<'s, S: Signal + ?Sized> {
+    signal: &'s S,
+    waiter: AsyncWaiter,
+}
+
+impl<'s, S: Signal + ?Sized> SignalFuture<'s, S> {
+    pub fn new(signal: &'s S) -> Self {
+        Self {
+            signal,
+            waiter: AsyncWaiter::default(),
+        }
+    }
+}
+
+impl<S: Signal + ?Sized> Future for SignalFuture<'_, S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.waiter.arm(this.signal, cx.waker());
+        if this.signal.is_signalled() {
+            this.waiter.disarm(this.signal);
+            Poll::Ready(())
+        } else {
+            this.waiter.pending(cx.waker())
+        }
+    }
+}
+
+impl<S: Signal + ?Sized> Drop for SignalFuture<'_, S> {
+    fn drop(&mut self) {
+        self.waiter.disarm(self.signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        task::Wake,
+    };
+
+    use super::*;
+    use crate::signal::SignallableData;
+
+    /// Polls straight from `wake`, as some executors do, recording whether the data was free
+    struct InlineWaker {
+        data: Arc<SignallableData<u32>>,
+        unlocked: AtomicBool,
+    }
+
+    impl Wake for InlineWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let unlocked = matches!(self.data.try_lock(), Ok(Some(lock)) if *lock == 1);
+            self.unlocked.store(unlocked, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn tasks_are_woken_after_the_lock_is_released() {
+        let data = Arc::new(SignallableData::new(0));
+        let inline = Arc::new(InlineWaker { data: data.clone(), unlocked: AtomicBool::new(false) });
+        let waker = Waker::from(inline.clone());
+        let mut future = std::pin::pin!(data.wait_for_signal_async());
+        assert!(future.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        *data.lock().unwrap() = 1;
+        assert!(inline.unlocked.load(Ordering::SeqCst));
+    }
+}
@@ -1,12 +1,13 @@
 use std::sync::{Arc, Condvar, Mutex};
 
-use super::{Signal, SignalResult};
+use super::{Signal, SignalResult, SignalWaiter, waiter::WaiterList};
 
 
 #[derive(Default, Debug)]
 pub struct IdleSignal {
     state: Arc<Mutex<bool>>,
     signal: Condvar,
+    waiters: WaiterList,
 }
 
 unsafe impl Send for IdleSignal {}
@@ -36,6 +37,16 @@ impl Signal for IdleSignal {
         *lock = value;
         drop(lock);
         self.signal.notify_all();
+        self.waiters.wake_all();
         old_val
     }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.waiters.register(waiter);
+        true
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.waiters.unregister(waiter);
+    }
 }
\ No newline at end of file
@@ -1,14 +1,41 @@
 mod signallable;
 mod idlesignal;
-use std::ops::Deref;
+mod waiter;
+mod future;
+mod cancellation;
+use std::{ops::Deref, sync::Arc};
 
 pub use idlesignal::IdleSignal;
-pub use signallable::SignallableData;
+pub use signallable::{SignallableData, SignallableLock};
+pub use waiter::{SignalWaiter, wait_all, wait_any};
+pub(crate) use waiter::WaiterList;
+pub use future::SignalFuture;
+pub use cancellation::{CallbackId, CancellationToken};
+pub(crate) use future::AsyncWaiter;
 
 pub trait Signal {
     fn is_signalled(&self) -> bool;
     fn wait_for_signal(&self) -> Result<(), SignalResult>;
     fn set_signal(&self, value: bool) -> bool;
+
+    /// Wakes `waiter` whenever the signal may have changed, until it is unregistered.
+    ///
+    /// Returns false if this isn't supported, in which case [`wait_any`] and [`wait_all`] poll it.
+    fn register_waiter(&self, _waiter: &Arc<SignalWaiter>) -> bool {
+        false
+    }
+
+    fn unregister_waiter(&self, _waiter: &Arc<SignalWaiter>) {}
+
+    /// Resolves once the signal is set, on any executor. A signal that can't
+    /// [register a waiter](Self::register_waiter) has its task woken again straight away, keeping
+    /// the executor busy while it waits.
+    fn wait_for_signal_async(&self) -> SignalFuture<'_, Self>
+    where
+        Self: Sized,
+    {
+        SignalFuture::new(self)
+    }
 }
 
 #[derive(Debug)]
@@ -1,10 +1,11 @@
 use std::{
+    mem::ManuallyDrop,
     ops::{Deref, DerefMut},
     sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
     time::Duration,
 };
 
-use super::{Signal, SignalResult};
+use super::{Signal, SignalResult, SignalWaiter, waiter::WaiterList};
 
 #[derive(Default, Debug)]
 struct DataSignalPair<T> {
@@ -13,7 +14,8 @@ struct DataSignalPair<T> {
 }
 
 pub struct SignallableLock<'sd, T> {
-    guard: MutexGuard<'sd, DataSignalPair<T>>,
+    /// Released by hand in `drop`, so waiters are only woken once the data can be locked again
+    guard: ManuallyDrop<MutexGuard<'sd, DataSignalPair<T>>>,
     source: &'sd SignallableData<T>,
 }
 
@@ -25,7 +27,10 @@ impl<'sd, T> SignallableLock<'sd, T> {
 
 impl<'sd, T> Drop for SignallableLock<'sd, T> {
     fn drop(&mut self) {
+        // SAFETY: the guard is never used again, the lock itself is being dropped
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
         self.source.condvar.notify_all();
+        self.source.waiters.wake_all();
     }
 }
 
@@ -52,6 +57,7 @@ impl<T> From<PoisonError<MutexGuard<'_, DataSignalPair<T>>>> for SignalResult {
 pub struct SignallableData<T> {
     data: Mutex<DataSignalPair<T>>,
     condvar: Condvar,
+    waiters: WaiterList,
 }
 
 unsafe impl<T> Send for SignallableData<T> {}
@@ -65,6 +71,7 @@ impl<T> SignallableData<T> {
                 signal: false,
             }),
             condvar: Default::default(),
+            waiters: Default::default(),
         }
     }
 
@@ -106,12 +113,18 @@ impl<'sd, T> SignallableData<T> {
         }
     }
 
+    /// Reads the data without waking anything waiting on it, which dropping a lock always does
+    pub fn peek<R>(&'sd self, read: impl FnOnce(&T, bool) -> R) -> Result<R, SignalResult> {
+        let guard = self.get_guard()?;
+        Ok(read(&guard.data, guard.signal))
+    }
+
     fn create_lock_thingy_idk(
         &'sd self,
         guard: MutexGuard<'sd, DataSignalPair<T>>,
     ) -> SignallableLock<'sd, T> {
         SignallableLock {
-            guard,
+            guard: ManuallyDrop::new(guard),
             source: self,
         }
     }
@@ -186,6 +199,16 @@ impl<T> Signal for SignallableData<T> {
         guard.signal = value;
         drop(guard);
         self.condvar.notify_all();
+        self.waiters.wake_all();
         old
     }
+
+    fn register_waiter(&self, waiter: &Arc<SignalWaiter>) -> bool {
+        self.waiters.register(waiter);
+        true
+    }
+
+    fn unregister_waiter(&self, waiter: &Arc<SignalWaiter>) {
+        self.waiters.unregister(waiter);
+    }
 }
@@ -0,0 +1,208 @@
+use std::{
+    sync::{
+        Arc, Condvar, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::Waker,
+    time::{Duration, Instant},
+};
+
+use super::Signal;
+
+/// How often signals that can't register a [`SignalWaiter`] are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Woken by the signals a [`wait_any`] or [`wait_all`] call, or a future, is watching whenever one
+/// of them may have changed.
+///
+/// [`Signal`] implementors outside this crate call [`wake`](Self::wake) on every waiter they were
+/// given through [`Signal::register_waiter`].
+#[derive(Default, Debug)]
+pub struct SignalWaiter {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+    /// The task to wake when waited on asynchronously
+    waker: Mutex<Option<Waker>>,
+}
+
+impl SignalWaiter {
+    pub fn wake(&self) {
+        let mut woken = self.woken.lock().unwrap_or_else(PoisonError::into_inner);
+        *woken = true;
+        self.condvar.notify_all();
+        drop(woken);
+        let waker = self.waker.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn set_waker(&self, waker: &Waker) {
+        let mut current = self.waker.lock().unwrap_or_else(PoisonError::into_inner);
+        if !current.as_ref().is_some_and(|current| current.will_wake(waker)) {
+            *current = Some(waker.clone());
+        }
+    }
+
+    /// Waits to be woken or for `deadline` to pass, then resets so the next call waits again
+    fn wait(&self, deadline: Option<Instant>) {
+        let mut woken = self.woken.lock().unwrap_or_else(PoisonError::into_inner);
+        while !*woken {
+            woken = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    self.condvar
+                        .wait_timeout(woken, left)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self.condvar.wait(woken).unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+        *woken = false;
+    }
+}
+
+/// The waiters registered with one signal
+#[derive(Default, Debug)]
+pub(crate) struct WaiterList {
+    /// Lets signals skip locking the list when nobody is waiting, which is nearly always
+    count: AtomicUsize,
+    waiters: Mutex<Vec<Arc<SignalWaiter>>>,
+}
+
+impl WaiterList {
+    pub(crate) fn register(&self, waiter: &Arc<SignalWaiter>) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(PoisonError::into_inner);
+        waiters.push(waiter.clone());
+        self.count.store(waiters.len(), Ordering::SeqCst);
+    }
+
+    pub(crate) fn unregister(&self, waiter: &Arc<SignalWaiter>) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = waiters.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            waiters.swap_remove(index);
+        }
+        self.count.store(waiters.len(), Ordering::SeqCst);
+    }
+
+    pub(crate) fn wake_all(&self) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        // Woken without the list locked, a waker that polls inline may register or unregister
+        let waiters = self.waiters.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        waiters.iter().for_each(|waiter| waiter.wake());
+    }
+}
+
+/// Waits on every signal in `signals` until `check` returns something or `deadline` passes
+fn wait_until<R>(
+    signals: &[&dyn Signal],
+    deadline: Option<Instant>,
+    mut check: impl FnMut() -> Option<R>,
+) -> Option<R> {
+    let waiter = Arc::new(SignalWaiter::default());
+    // Registering before the first check means no change can be missed in between
+    let registered = signals
+        .iter()
+        .map(|signal| signal.register_waiter(&waiter))
+        .collect::<Vec<_>>();
+    let polled = registered.contains(&false);
+    let result = loop {
+        if let Some(result) = check() {
+            break Some(result);
+        }
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            break None;
+        }
+        let wake_by = if polled {
+            let poll = now + POLL_INTERVAL;
+            Some(deadline.map_or(poll, |deadline| deadline.min(poll)))
+        } else {
+            deadline
+        };
+        waiter.wait(wake_by);
+    };
+    signals.iter().for_each(|signal| signal.unregister_waiter(&waiter));
+    result
+}
+
+/// Waits until any of `signals` is set, or until `deadline` passes.
+///
+/// Returns the index of the set signal, the lowest one if several are set, or `None` if the
+/// deadline passed first. Returns `None` straight away if there are no signals to wait on.
+pub fn wait_any(signals: &[&dyn Signal], deadline: Option<Instant>) -> Option<usize> {
+    if signals.is_empty() {
+        return None;
+    }
+    wait_until(signals, deadline, || {
+        signals.iter().position(|signal| signal.is_signalled())
+    })
+}
+
+/// Waits until all of `signals` are set at once, or until `deadline` passes.
+///
+/// If the deadline passed first, returns the indices of the signals that still aren't set.
+pub fn wait_all(signals: &[&dyn Signal], deadline: Option<Instant>) -> Result<(), Vec<usize>> {
+    let all_set = || signals.iter().all(|signal| signal.is_signalled()).then_some(());
+    if wait_until(signals, deadline, all_set).is_some() {
+        return Ok(());
+    }
+    let unset = signals
+        .iter()
+        .enumerate()
+        .filter(|(_, signal)| !signal.is_signalled())
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if unset.is_empty() { Ok(()) } else { Err(unset) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        queue::ThreadSafeQueue,
+        signal::{IdleSignal, SignallableData},
+    };
+
+    #[test]
+    fn wait_any_and_all_across_signal_types() {
+        let shutdown = Arc::new(IdleSignal::new());
+        let state = Arc::new(SignallableData::new(0u32));
+        let queue = ThreadSafeQueue::<u32>::new();
+        let soon = || Some(Instant::now() + Duration::from_millis(20));
+
+        assert_eq!(wait_any(&[shutdown.as_ref(), &queue.ready()], soon()), None);
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                queue.enqueue(1)
+            })
+        };
+        assert_eq!(wait_any(&[shutdown.as_ref(), &queue.ready()], None), Some(1));
+        assert!(producer.join().unwrap().is_ok());
+        assert_eq!(queue.try_dequeue(), Some(1));
+
+        let signals: [&dyn Signal; 3] = [shutdown.as_ref(), state.as_ref(), &queue];
+        assert_eq!(wait_all(&signals, soon()), Err(vec![0, 1, 2]));
+        let signaller = {
+            let (shutdown, state) = (shutdown.clone(), state.clone());
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                state.set_signal(true);
+                shutdown.set_signal(true);
+            })
+        };
+        assert_eq!(wait_all(&signals[..2], None), Ok(()));
+        signaller.join().unwrap();
+        assert_eq!(wait_all(&signals, soon()), Err(vec![2]));
+        queue.cancel();
+        assert_eq!(wait_all(&signals, None), Ok(()));
+    }
+}